@@ -0,0 +1,131 @@
+// compares parsing into the boxed `Expr` tree against parsing into an
+// `ExprArena`, reporting wall time plus the number of allocations and
+// peak heap usage seen while parsing. run with `cargo bench --bench ast_arena`
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use lox_interpreter::lexer::scan_tokens;
+use lox_interpreter::parser::Parser;
+use lox_interpreter::syntax::arena_expr::ExprArena;
+use lox_interpreter::syntax::printer::AstPrinter;
+
+// global allocator that keeps count of what goes through it
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static CURRENT_BYTES: AtomicUsize = AtomicUsize::new(0);
+static PEAK_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        let current = CURRENT_BYTES.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+        PEAK_BYTES.fetch_max(current, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        CURRENT_BYTES.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+const ITERATIONS: u32 = 20;
+const DEPTH: u32 = 15;
+
+// a balanced expression with 2^depth leaves, balanced so that dropping
+// the boxed tree doesn't recurse deep enough to blow the stack
+fn balanced_source(depth: u32, counter: &mut u32, out: &mut String) {
+    if depth == 0 {
+        *counter += 1;
+        out.push_str(&counter.to_string());
+        return;
+    }
+    let ops = ["+", "-", "*", "/", "==", "<"];
+    out.push('(');
+    balanced_source(depth - 1, counter, out);
+    out.push_str(&format!(" {} ", ops[(depth as usize) % ops.len()]));
+    if depth.is_multiple_of(3) {
+        out.push('-');
+    }
+    balanced_source(depth - 1, counter, out);
+    out.push(')');
+}
+
+struct Measurement {
+    time: Duration,
+    allocations: usize,
+    peak_bytes: usize,
+}
+
+// run f once while watching the allocator, then time it over ITERATIONS.
+// the tree is dropped inside f, so peak bytes covers the whole tree
+fn measure<T>(f: impl Fn() -> T) -> Measurement {
+    let baseline = CURRENT_BYTES.load(Ordering::Relaxed);
+    PEAK_BYTES.store(baseline, Ordering::Relaxed);
+    ALLOCATIONS.store(0, Ordering::Relaxed);
+    black_box(f());
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let peak_bytes = PEAK_BYTES.load(Ordering::Relaxed) - baseline;
+
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        black_box(f());
+    }
+    let time = start.elapsed() / ITERATIONS;
+    Measurement {
+        time,
+        allocations,
+        peak_bytes,
+    }
+}
+
+fn report(name: &str, m: &Measurement) {
+    println!(
+        "{:<8} {:>10.2?} per parse {:>10} allocations {:>10} KiB peak",
+        name,
+        m.time,
+        m.allocations,
+        m.peak_bytes / 1024
+    );
+}
+
+fn main() {
+    let mut source = String::new();
+    balanced_source(DEPTH, &mut 0, &mut source);
+    // lex up front so both sides only measure building the tree
+    let tokens: Vec<_> = scan_tokens(&source).collect();
+    println!("parsing {} tokens, {} iterations", tokens.len(), ITERATIONS);
+
+    // the two trees have to agree before comparing how they were built
+    let printer = AstPrinter {};
+    let expr = Parser::new(tokens.iter().cloned()).parse().ok().unwrap();
+    let mut parser = Parser::with_builder(tokens.iter().cloned(), ExprArena::new());
+    let root = parser.parse().ok().unwrap();
+    assert_eq!(
+        printer.print(&expr),
+        printer.print_arena(&parser.into_builder(), root)
+    );
+    drop(expr);
+
+    // both sides pay for cloning the tokens, which is the same for each
+    let tokens_only = measure(|| tokens.iter().for_each(|t| drop(black_box(t.clone()))));
+    let boxed = measure(|| Parser::new(tokens.iter().cloned()).parse().ok().unwrap());
+    let arena = measure(|| {
+        // there is never more than one node per token, so one allocation
+        // up front is enough for the whole tree
+        let arena = ExprArena::with_capacity(tokens.len());
+        let mut parser = Parser::with_builder(tokens.iter().cloned(), arena);
+        let root = parser.parse().ok().unwrap();
+        (root, parser.into_builder())
+    });
+
+    report("tokens", &tokens_only);
+    report("boxed", &boxed);
+    report("arena", &arena);
+}
@@ -0,0 +1,13 @@
+use std::path::Path;
+
+// fail the build if the generated syntax trees in src/syntax don't match
+// what define_ast would generate from src/syntax/ast.schema, so a hand
+// edit can't quietly get lost the next time they're regenerated.
+// regenerate them with `cargo run -p define_ast -- src/syntax`
+fn main() {
+    println!("cargo:rerun-if-changed=src/syntax");
+    if let Err(err) = define_ast::check(Path::new("src/syntax")) {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
+}
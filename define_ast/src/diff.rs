@@ -0,0 +1,166 @@
+// lines of context kept around each change, like `diff -u`
+const CONTEXT: usize = 3;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Op {
+    Equal,
+    Delete,
+    Insert,
+}
+
+// a unified diff turning `old` into `new`, empty when they're the same.
+// it finds a longest common subsequence of lines, which is quadratic but
+// plenty for the size of file we generate
+pub fn unified_diff(old: &str, new: &str, old_name: &str, new_name: &str) -> String {
+    let a: Vec<&str> = old.lines().collect();
+    let b: Vec<&str> = new.lines().collect();
+    if a == b {
+        return String::new();
+    }
+
+    // lcs[i][j] is the length of the lcs of a[i..] and b[j..]
+    let mut lcs = vec![vec![0u32; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    // each op along with the line in a and b it happens at
+    let mut ops: Vec<(Op, usize, usize)> = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            ops.push((Op::Equal, i, j));
+            i += 1;
+            j += 1;
+        } else if i < a.len() && (j == b.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            // deletions go before insertions, like `diff -u`
+            ops.push((Op::Delete, i, j));
+            i += 1;
+        } else {
+            ops.push((Op::Insert, i, j));
+            j += 1;
+        }
+    }
+
+    let mut out = format!("--- {}\n+++ {}\n", old_name, new_name);
+    let changes: Vec<usize> = (0..ops.len()).filter(|&k| ops[k].0 != Op::Equal).collect();
+    let mut k = 0;
+    while k < changes.len() {
+        // grow the hunk while the next change is close enough that
+        // their context would overlap
+        let start = changes[k].saturating_sub(CONTEXT);
+        let mut last = changes[k];
+        while k + 1 < changes.len() && changes[k + 1] <= last + 2 * CONTEXT + 1 {
+            k += 1;
+            last = changes[k];
+        }
+        k += 1;
+        let end = (last + CONTEXT + 1).min(ops.len());
+        out.push_str(hunk(&ops[start..end], &a, &b).as_str());
+    }
+    out
+}
+
+fn hunk(ops: &[(Op, usize, usize)], a: &[&str], b: &[&str]) -> String {
+    let old_len = ops.iter().filter(|op| op.0 != Op::Insert).count();
+    let new_len = ops.iter().filter(|op| op.0 != Op::Delete).count();
+    // an empty range is given as the line before it
+    let range = |first: usize, len: usize| match len {
+        0 => format!("{},0", first),
+        _ => format!("{},{}", first + 1, len),
+    };
+    let mut out = format!(
+        "@@ -{} +{} @@\n",
+        range(ops[0].1, old_len),
+        range(ops[0].2, new_len)
+    );
+    for &(op, i, j) in ops {
+        match op {
+            Op::Equal => out.push_str(format!(" {}\n", a[i]).as_str()),
+            Op::Delete => out.push_str(format!("-{}\n", a[i]).as_str()),
+            Op::Insert => out.push_str(format!("+{}\n", b[j]).as_str()),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::unified_diff;
+
+    fn diff(old: &str, new: &str) -> String {
+        unified_diff(old, new, "old", "new")
+    }
+
+    // the numbers from 1 to `count`, one per line
+    fn lines(count: usize) -> String {
+        (1..=count).map(|n| format!("{}\n", n)).collect()
+    }
+
+    // the same, with the given lines spelled out instead
+    fn changed(count: usize, changes: &[(usize, &str)]) -> String {
+        (1..=count)
+            .map(|n| match changes.iter().find(|(line, _)| *line == n) {
+                Some((_, text)) => format!("{}\n", text),
+                None => format!("{}\n", n),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn same_lines_give_no_diff() {
+        assert_eq!(diff("", ""), "");
+        assert_eq!(diff("a\nb\n", "a\nb\n"), "");
+        assert_eq!(diff("a\r\nb\r\n", "a\nb\n"), "");
+        assert_eq!(diff("a\nb", "a\nb\n"), "");
+    }
+
+    #[test]
+    fn a_changed_line() {
+        assert_eq!(
+            diff("a\nb\nc\n", "a\nB\nc\n"),
+            "--- old\n+++ new\n@@ -1,3 +1,3 @@\n a\n-b\n+B\n c\n"
+        );
+    }
+
+    #[test]
+    fn added_and_removed_files() {
+        assert_eq!(
+            diff("", "a\nb\n"),
+            "--- old\n+++ new\n@@ -0,0 +1,2 @@\n+a\n+b\n"
+        );
+        assert_eq!(diff("a\n", ""), "--- old\n+++ new\n@@ -1,1 +0,0 @@\n-a\n");
+    }
+
+    #[test]
+    fn context_is_three_lines() {
+        let new = changed(10, &[(5, "five")]);
+        assert_eq!(
+            diff(&lines(10), &new),
+            "--- old\n+++ new\n@@ -2,7 +2,7 @@\n 2\n 3\n 4\n-5\n+five\n 6\n 7\n 8\n"
+        );
+    }
+
+    #[test]
+    fn changes_far_apart_get_their_own_hunks() {
+        let new = changed(20, &[(2, "two"), (19, "nineteen")]);
+        let diff = diff(&lines(20), &new);
+        assert_eq!(diff.matches("@@ -").count(), 2, "{}", diff);
+        assert!(diff.contains("@@ -1,5 +1,5 @@\n 1\n-2\n+two\n 3\n 4\n 5\n"));
+        assert!(diff.contains("@@ -16,5 +16,5 @@\n 16\n 17\n 18\n-19\n+nineteen\n 20\n"));
+    }
+
+    #[test]
+    fn changes_with_overlapping_context_share_a_hunk() {
+        let new = changed(20, &[(5, "five"), (11, "eleven")]);
+        let diff = diff(&lines(20), &new);
+        assert_eq!(diff.matches("@@ -").count(), 1, "{}", diff);
+        assert!(diff.contains("@@ -2,13 +2,13 @@\n"));
+    }
+}
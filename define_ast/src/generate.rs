@@ -0,0 +1,837 @@
+use std::collections::HashSet;
+
+use crate::schema::{Field, FieldType, Tree, TreeType};
+
+// generated code is indented with spaces and laid out the way rustfmt
+// would, so `cargo fmt` leaves the generated files alone
+const INDENT: &str = "    ";
+const MAX_WIDTH: usize = 100;
+// rustfmt keeps struct literals on one line while the fields fit in this
+const STRUCT_LIT_WIDTH: usize = 18;
+// every node type gets these, with EQ_DOC above the boxed tree's enum to
+// say what they mean for node ids
+const DERIVES: &str = "#[derive(Debug, Clone, PartialEq)]\n";
+const EQ_DOC: &str = "\
+// `==` means the same nodes. ids are compared too, so two parses of the
+// same source are never `==`, compare those with `structural_eq`. a clone
+// keeps the ids of the original, it is a copy of the same nodes and side
+// tables keyed by id apply to both
+";
+
+impl TreeType {
+    pub fn full_name(&self) -> String {
+        format!("{}{}", self.class_name, self.base_name)
+    }
+
+    pub fn snake_case_full_name(&self) -> String {
+        format!(
+            "{}_{}",
+            self.class_name.to_lowercase(),
+            self.base_name.to_lowercase()
+        )
+    }
+
+    // the same node with boxed children swapped for arena handles
+    pub fn in_arena(&self) -> TreeType {
+        TreeType {
+            line: self.line,
+            base_name: self.base_name.clone(),
+            class_name: self.class_name.clone(),
+            fields: self
+                .fields
+                .iter()
+                .map(|field| Field {
+                    name: field.name.clone(),
+                    ty: field.ty.in_arena(&self.base_name),
+                })
+                .collect(),
+        }
+    }
+
+    pub fn to_struct(&self) -> String {
+        let mut out = format!("{}pub struct {} {{\n", DERIVES, self.full_name());
+        // every node gets a unique id and the span of source it covers,
+        // so later passes can key side tables and diagnostics off of it
+        out.push_str(format!("{INDENT}pub id: NodeId,\n").as_str());
+        for field in self.fields.iter() {
+            out.push_str(format!("{INDENT}pub {}: {},\n", field.name, field.ty).as_str());
+        }
+        out.push_str(format!("{INDENT}pub span: Span,\n").as_str());
+        out.push_str("}\n\n");
+        out
+    }
+}
+
+// the header every generated file starts with, along with the imports of
+// any other trees the nodes refer to, and of what the structural impls
+// need when the file has them. the crate imports are sorted the way
+// rustfmt sorts them, so the file passes `cargo fmt --check`
+fn file_header(tree: &Tree, names: &HashSet<&str>, structural: bool) -> String {
+    let mut out = String::new();
+    out.push_str("// generated by define_ast from ast.schema, do not edit by hand\n\n");
+    if structural {
+        out.push_str("use std::hash::{Hash, Hasher};\n\n");
+    }
+    let mut imports = vec![
+        "pub use crate::lexer::token::{Literal, Span, Token};".to_string(),
+        "pub use crate::syntax::NodeId;".to_string(),
+    ];
+    if structural {
+        imports.push("use crate::syntax::Structural;".to_string());
+    }
+    for node in &tree.nodes {
+        for field in &node.fields {
+            for other in field.ty.trees(names) {
+                if other != tree.name {
+                    imports.push(format!(
+                        "use crate::syntax::{}::{};",
+                        other.to_lowercase(),
+                        other
+                    ));
+                }
+            }
+        }
+    }
+    imports.sort_by_key(|import| import_order(import));
+    imports.dedup();
+    for import in imports {
+        out.push_str(format!("{}\n", import).as_str());
+    }
+    out.push('\n');
+    out
+}
+
+// rustfmt orders imports by their path whatever their visibility, a
+// segment at a time, with modules before types
+fn import_order(import: &str) -> Vec<(bool, String)> {
+    let path = import.trim_start_matches("pub ").trim_start_matches("use ");
+    path.split("::")
+        .map(|segment| {
+            let starts_upper = segment.starts_with(|c: char| c.is_ascii_uppercase());
+            (starts_upper, segment.to_string())
+        })
+        .collect()
+}
+
+// the files for one tree, as (file name, contents)
+pub fn define_ast(tree: &Tree, names: &HashSet<&str>) -> Vec<(String, String)> {
+    let base_name = tree.name.as_str();
+    let mut out = file_header(tree, names, true);
+    out.push_str(EQ_DOC);
+    out.push_str(define_base_enum(base_name, &tree.nodes).as_str());
+
+    // create struct for each of the rules in the base enum
+    for tree_type in tree.nodes.iter() {
+        out.push_str(tree_type.to_struct().as_str());
+    }
+    out.push_str(define_visitor(base_name, &tree.nodes).as_str());
+    out.push_str(define_visitor_mut(base_name, &tree.nodes).as_str());
+    out.push_str(define_fold(base_name, &tree.nodes).as_str());
+    out.push_str(define_structural(base_name, &tree.nodes).as_str());
+
+    let mut files = vec![(format!("{}.rs", base_name.to_lowercase()), out)];
+    if tree.arena {
+        files.push((
+            format!("arena_{}.rs", base_name.to_lowercase()),
+            define_arena_ast(tree, names),
+        ));
+    }
+    files
+}
+
+fn define_base_enum(base_name: &str, tree_types: &[TreeType]) -> String {
+    let mut out = String::new();
+    out.push_str(format!("{}pub enum {} {{\n", DERIVES, base_name).as_str());
+    for tree_type in tree_types.iter() {
+        out.push_str(
+            format!(
+                "{INDENT}{}({}),\n",
+                tree_type.class_name,
+                tree_type.full_name()
+            )
+            .as_str(),
+        );
+    }
+    out.push_str("}\n\n");
+    out
+}
+
+// accessors for the id and span every node carries, as methods on the base enum
+fn define_node_accessors(base_name: &str, tree_types: &[TreeType]) -> String {
+    let mut out = String::new();
+    for (accessor, accessor_type) in [("id", "NodeId"), ("span", "Span")] {
+        out.push_str(
+            format!(
+                "\n{INDENT}pub fn {}(&self) -> {} {{\n",
+                accessor, accessor_type
+            )
+            .as_str(),
+        );
+        out.push_str(format!("{INDENT}{INDENT}match self {{\n").as_str());
+        for ttype in tree_types.iter() {
+            out.push_str(
+                format!(
+                    "{INDENT}{INDENT}{INDENT}{}::{}(e) => e.{},\n",
+                    base_name, ttype.class_name, accessor
+                )
+                .as_str(),
+            );
+        }
+        out.push_str(format!("{INDENT}{INDENT}}}\n{INDENT}}}\n").as_str());
+    }
+    out
+}
+
+pub fn define_visitor(base_name: &str, tree_types: &[TreeType]) -> String {
+    let mut out = String::new();
+    out.push_str(format!("pub trait {}Visitor<T> {{\n", base_name).as_str());
+    for ttype in tree_types.iter() {
+        out.push_str(
+            format!(
+                "{INDENT}fn visit_{}_{}(&self, e: &{}) -> T;\n",
+                ttype.class_name.to_lowercase(),
+                ttype.base_name.to_lowercase(),
+                ttype.full_name()
+            )
+            .as_str(),
+        );
+    }
+    out.push_str("}\n\n");
+
+    // create walk_* function for each type
+
+    // first, create walk_<base_name> to match to each enum type
+    out.push_str(format!("impl {} {{\n", base_name).as_str());
+    out.push_str(
+        format!(
+            "{INDENT}pub fn walk_{}<T>(&self, v: &dyn {}Visitor<T>) -> T {{\n",
+            base_name.to_lowercase(),
+            base_name
+        )
+        .as_str(),
+    );
+    out.push_str(format!("{INDENT}{INDENT}match self {{\n").as_str());
+    for ttype in tree_types.iter() {
+        out.push_str(
+            format!(
+                "{INDENT}{INDENT}{INDENT}{}::{}(e) => e.walk_{}(v),\n",
+                base_name,
+                ttype.class_name,
+                ttype.snake_case_full_name()
+            )
+            .as_str(),
+        );
+    }
+    out.push_str(format!("{INDENT}{INDENT}}}\n{INDENT}}}\n").as_str());
+
+    out.push_str(define_node_accessors(base_name, tree_types).as_str());
+    out.push_str("}\n\n");
+
+    // create impl block with new and walk_* for each tree_type
+    // now create walk_* for each of the tree_types
+    for ttype in tree_types.iter() {
+        out.push_str(format!("impl {} {{\n", ttype.full_name()).as_str());
+        // define a new function for each type
+        out.push_str(define_new_func_for_type(ttype).as_str());
+
+        // define walk_* for each type
+        out.push_str(
+            format!(
+                "{INDENT}pub fn walk_{}<T>(&self, v: &dyn {}Visitor<T>) -> T {{\n",
+                ttype.snake_case_full_name(),
+                base_name,
+            )
+            .as_str(),
+        );
+        out.push_str(
+            format!(
+                "{INDENT}{INDENT}v.visit_{}(self)\n",
+                ttype.snake_case_full_name()
+            )
+            .as_str(),
+        );
+        out.push_str(format!("{INDENT}}}\n}}\n\n").as_str());
+    }
+    out
+}
+
+// a function signature, broken one parameter per line the way rustfmt
+// does once it doesn't fit on one line
+fn define_fn_signature(name: &str, params: &[String], ret_type: Option<&str>) -> String {
+    let ret = ret_type.map_or(String::new(), |ret_type| format!(" -> {}", ret_type));
+    let signature = format!("pub fn {}({}){} {{", name, params.join(", "), ret);
+    if signature.len() <= MAX_WIDTH {
+        return format!("{}\n", signature);
+    }
+    let mut out = format!("pub fn {}(\n", name);
+    for param in params {
+        out.push_str(format!("{INDENT}{},\n", param).as_str());
+    }
+    out.push_str(format!("){} {{\n", ret).as_str());
+    out
+}
+
+// statement visiting every child of `base_name` that `value` (a reference
+// to a field of type `ty`) holds, or None if it can't hold any. children
+// from other trees are left alone, they have their own visitors
+fn visit_children(base_name: &str, ty: &FieldType, value: &str, depth: usize) -> Option<String> {
+    let indent = INDENT.repeat(depth + 1);
+    let x = format!("x{}", depth);
+    match ty {
+        FieldType::Named(name) if name == base_name => Some(format!(
+            "{indent}v.visit_{}({});\n",
+            base_name.to_lowercase(),
+            value
+        )),
+        FieldType::Named(_) => None,
+        FieldType::Boxed(inner) => visit_children(base_name, inner, value, depth),
+        FieldType::Option(inner) => {
+            let body = visit_children(base_name, inner, &x, depth + 1)?;
+            Some(format!(
+                "{indent}if let Some({x}) = {value} {{\n{body}{indent}}}\n"
+            ))
+        }
+        FieldType::List(inner) => {
+            let body = visit_children(base_name, inner, &x, depth + 1)?;
+            Some(format!("{indent}for {x} in {value} {{\n{body}{indent}}}\n"))
+        }
+    }
+}
+
+// a mutable visitor, unlike `<Base>Visitor` it takes `&mut self` so it can
+// keep state as it goes. every method defaults to visiting the node's
+// children through the matching `walk_*_mut` function, so a visitor only
+// overrides the nodes it's interested in
+pub fn define_visitor_mut(base_name: &str, tree_types: &[TreeType]) -> String {
+    let lower = base_name.to_lowercase();
+    let visitor = format!("{}VisitorMut", base_name);
+    let generics = format!("<V: {} + ?Sized>", visitor);
+
+    let mut out = String::new();
+    out.push_str(format!("pub trait {} {{\n", visitor).as_str());
+    out.push_str(
+        format!(
+            "{INDENT}fn visit_{lower}(&mut self, e: &{base_name}) {{\n{INDENT}{INDENT}walk_{lower}_mut(self, e)\n{INDENT}}}\n"
+        )
+        .as_str(),
+    );
+    for ttype in tree_types.iter() {
+        out.push_str(
+            format!(
+                "\n{INDENT}fn visit_{0}(&mut self, e: &{1}) {{\n{INDENT}{INDENT}walk_{0}_mut(self, e)\n{INDENT}}}\n",
+                ttype.snake_case_full_name(),
+                ttype.full_name()
+            )
+            .as_str(),
+        );
+    }
+    out.push_str("}\n\n");
+
+    out.push_str(
+        define_fn_signature(
+            format!("walk_{}_mut{}", lower, generics).as_str(),
+            &["v: &mut V".to_string(), format!("e: &{}", base_name)],
+            None,
+        )
+        .as_str(),
+    );
+    out.push_str(format!("{INDENT}match e {{\n").as_str());
+    for ttype in tree_types.iter() {
+        out.push_str(
+            format!(
+                "{INDENT}{INDENT}{}::{}(e) => v.visit_{}(e),\n",
+                base_name,
+                ttype.class_name,
+                ttype.snake_case_full_name()
+            )
+            .as_str(),
+        );
+    }
+    out.push_str(format!("{INDENT}}}\n}}\n\n").as_str());
+
+    for ttype in tree_types.iter() {
+        let body: String = ttype
+            .fields
+            .iter()
+            .filter_map(|field| {
+                visit_children(base_name, &field.ty, &format!("&e.{}", field.name), 0)
+            })
+            .collect();
+        let name = format!("walk_{}_mut{}", ttype.snake_case_full_name(), generics);
+        if body.is_empty() {
+            let params = [
+                "_: &mut V".to_string(),
+                format!("_: &{}", ttype.full_name()),
+            ];
+            let signature = define_fn_signature(&name, &params, None);
+            out.push_str(format!("{}}}\n\n", signature.trim_end()).as_str());
+        } else {
+            let params = [
+                "v: &mut V".to_string(),
+                format!("e: &{}", ttype.full_name()),
+            ];
+            out.push_str(define_fn_signature(&name, &params, None).as_str());
+            out.push_str(body.as_str());
+            out.push_str("}\n\n");
+        }
+    }
+    out
+}
+
+// an expression that folds every child of `base_name` held in `value`,
+// an owned value of type `ty`, giving back a value of the same type
+fn fold_children(base_name: &str, ty: &FieldType, value: &str, depth: usize) -> Option<String> {
+    let x = format!("x{}", depth);
+    match ty {
+        FieldType::Named(name) if name == base_name => {
+            Some(format!("f.fold_{}({})", base_name.to_lowercase(), value))
+        }
+        FieldType::Named(_) => None,
+        FieldType::Boxed(inner) => {
+            let inner = fold_children(base_name, inner, &format!("*{}", value), depth)?;
+            Some(format!("Box::new({})", inner))
+        }
+        FieldType::Option(inner) => {
+            let inner = fold_children(base_name, inner, &x, depth + 1)?;
+            Some(format!("{}.map(|{}| {})", value, x, inner))
+        }
+        FieldType::List(inner) => {
+            let inner = fold_children(base_name, inner, &x, depth + 1)?;
+            Some(format!(
+                "{}.into_iter().map(|{}| {}).collect()",
+                value, x, inner
+            ))
+        }
+    }
+}
+
+// a fold takes a tree by value and builds a new one. each method gets
+// the node and returns whatever should replace it, which needn't be the
+// same kind of node, so passes like constant folding can rewrite the tree.
+// the defaults fold the node's children and put it back together
+pub fn define_fold(base_name: &str, tree_types: &[TreeType]) -> String {
+    let lower = base_name.to_lowercase();
+    let fold = format!("{}Fold", base_name);
+    let generics = format!("<F: {} + ?Sized>", fold);
+
+    let mut out = String::new();
+    out.push_str(format!("pub trait {} {{\n", fold).as_str());
+    out.push_str(
+        format!(
+            "{INDENT}fn fold_{lower}(&mut self, e: {base_name}) -> {base_name} {{\n{INDENT}{INDENT}fold_{lower}(self, e)\n{INDENT}}}\n"
+        )
+        .as_str(),
+    );
+    for ttype in tree_types.iter() {
+        out.push_str(
+            format!(
+                "\n{INDENT}fn fold_{0}(&mut self, e: {1}) -> {2} {{\n{INDENT}{INDENT}fold_{0}(self, e)\n{INDENT}}}\n",
+                ttype.snake_case_full_name(),
+                ttype.full_name(),
+                base_name
+            )
+            .as_str(),
+        );
+    }
+    out.push_str("}\n\n");
+
+    out.push_str(
+        define_fn_signature(
+            format!("fold_{}{}", lower, generics).as_str(),
+            &["f: &mut F".to_string(), format!("e: {}", base_name)],
+            Some(base_name),
+        )
+        .as_str(),
+    );
+    out.push_str(format!("{INDENT}match e {{\n").as_str());
+    for ttype in tree_types.iter() {
+        out.push_str(
+            format!(
+                "{INDENT}{INDENT}{}::{}(e) => f.fold_{}(e),\n",
+                base_name,
+                ttype.class_name,
+                ttype.snake_case_full_name()
+            )
+            .as_str(),
+        );
+    }
+    out.push_str(format!("{INDENT}}}\n}}\n\n").as_str());
+
+    for ttype in tree_types.iter() {
+        let folded: Vec<(String, Option<String>)> = ttype
+            .fields
+            .iter()
+            .map(|field| {
+                let value = format!("e.{}", field.name);
+                (
+                    field.name.clone(),
+                    fold_children(base_name, &field.ty, &value, 0),
+                )
+            })
+            .collect();
+        let name = format!("fold_{}{}", ttype.snake_case_full_name(), generics);
+        let node = format!("e: {}", ttype.full_name());
+        if folded.iter().all(|(_, fold)| fold.is_none()) {
+            // nothing to fold, hand the node straight back
+            let params = ["_: &mut F".to_string(), node];
+            out.push_str(define_fn_signature(&name, &params, Some(base_name)).as_str());
+            out.push_str(
+                format!("{INDENT}{}::{}(e)\n}}\n\n", base_name, ttype.class_name).as_str(),
+            );
+            continue;
+        }
+        let params = ["f: &mut F".to_string(), node];
+        out.push_str(define_fn_signature(&name, &params, Some(base_name)).as_str());
+        out.push_str(
+            format!(
+                "{INDENT}{}::{}({} {{\n",
+                base_name,
+                ttype.class_name,
+                ttype.full_name()
+            )
+            .as_str(),
+        );
+        out.push_str(format!("{INDENT}{INDENT}id: e.id,\n").as_str());
+        for (field_name, fold) in folded {
+            let value = fold.unwrap_or_else(|| format!("e.{}", field_name));
+            out.push_str(format!("{INDENT}{INDENT}{}: {},\n", field_name, value).as_str());
+        }
+        out.push_str(format!("{INDENT}{INDENT}span: e.span,\n").as_str());
+        out.push_str(format!("{INDENT}}})\n}}\n\n").as_str());
+    }
+    out
+}
+
+// `Structural` impls for every node, comparing and hashing the fields
+// declared in the schema and skipping the id and span every node has
+pub fn define_structural(base_name: &str, tree_types: &[TreeType]) -> String {
+    let mut out = String::new();
+    out.push_str(format!("impl Structural for {} {{\n", base_name).as_str());
+    out.push_str(format!("{INDENT}fn structural_eq(&self, other: &Self) -> bool {{\n").as_str());
+    out.push_str(format!("{INDENT}{INDENT}match (self, other) {{\n").as_str());
+    for ttype in tree_types.iter() {
+        out.push_str(
+            format!(
+                "{INDENT}{INDENT}{INDENT}({0}::{1}(a), {0}::{1}(b)) => a.structural_eq(b),\n",
+                base_name, ttype.class_name
+            )
+            .as_str(),
+        );
+    }
+    if tree_types.len() > 1 {
+        out.push_str(format!("{INDENT}{INDENT}{INDENT}_ => false,\n").as_str());
+    }
+    out.push_str(format!("{INDENT}{INDENT}}}\n{INDENT}}}\n\n").as_str());
+    out.push_str(
+        format!("{INDENT}fn structural_hash<H: Hasher>(&self, state: &mut H) {{\n").as_str(),
+    );
+    out.push_str(format!("{INDENT}{INDENT}std::mem::discriminant(self).hash(state);\n").as_str());
+    out.push_str(format!("{INDENT}{INDENT}match self {{\n").as_str());
+    for ttype in tree_types.iter() {
+        out.push_str(
+            format!(
+                "{INDENT}{INDENT}{INDENT}{}::{}(e) => e.structural_hash(state),\n",
+                base_name, ttype.class_name
+            )
+            .as_str(),
+        );
+    }
+    out.push_str(format!("{INDENT}{INDENT}}}\n{INDENT}}}\n}}\n\n").as_str());
+
+    for ttype in tree_types.iter() {
+        out.push_str(format!("impl Structural for {} {{\n", ttype.full_name()).as_str());
+        if ttype.fields.is_empty() {
+            out.push_str(
+                format!("{INDENT}fn structural_eq(&self, _: &Self) -> bool {{\n").as_str(),
+            );
+            out.push_str(format!("{INDENT}{INDENT}true\n{INDENT}}}\n\n").as_str());
+            out.push_str(
+                format!("{INDENT}fn structural_hash<H: Hasher>(&self, _: &mut H) {{}}\n}}\n\n")
+                    .as_str(),
+            );
+            continue;
+        }
+        out.push_str(
+            format!("{INDENT}fn structural_eq(&self, other: &Self) -> bool {{\n").as_str(),
+        );
+        let comparisons: Vec<String> = ttype
+            .fields
+            .iter()
+            .map(|field| format!("self.{0}.structural_eq(&other.{0})", field.name))
+            .collect();
+        // one line if it fits, otherwise one comparison per line
+        let one_line = format!("{INDENT}{INDENT}{}", comparisons.join(" && "));
+        if one_line.len() <= MAX_WIDTH {
+            out.push_str(format!("{}\n", one_line).as_str());
+        } else {
+            out.push_str(format!("{INDENT}{INDENT}{}\n", comparisons[0]).as_str());
+            for comparison in &comparisons[1..] {
+                out.push_str(format!("{INDENT}{INDENT}{INDENT}&& {}\n", comparison).as_str());
+            }
+        }
+        out.push_str(format!("{INDENT}}}\n\n").as_str());
+        out.push_str(
+            format!("{INDENT}fn structural_hash<H: Hasher>(&self, state: &mut H) {{\n").as_str(),
+        );
+        for field in &ttype.fields {
+            out.push_str(
+                format!(
+                    "{INDENT}{INDENT}self.{}.structural_hash(state);\n",
+                    field.name
+                )
+                .as_str(),
+            );
+        }
+        out.push_str(format!("{INDENT}}}\n}}\n\n").as_str());
+    }
+    out
+}
+
+fn define_new_func_for_type(ttype: &TreeType) -> String {
+    let mut params = Vec::new();
+    let mut names = Vec::new();
+    for field in &ttype.fields {
+        params.push(format!("{}: {}", field.name, field.ty));
+        names.push(field.name.clone());
+    }
+    // ids are handed out by new, the span has to come from the caller
+    params.push("span: Span".to_string());
+    names.insert(0, "id: NodeId::fresh()".to_string());
+    names.push("span".to_string());
+    let ret_type = ttype.full_name();
+
+    // put each of the fields as parameters for new, one per line
+    // if the signature gets too long
+    let mut out = String::new();
+    let signature = format!(
+        "{INDENT}pub fn new({}) -> {} {{",
+        params.join(", "),
+        ret_type
+    );
+    if signature.len() <= MAX_WIDTH {
+        out.push_str(format!("{}\n", signature).as_str());
+    } else {
+        out.push_str(format!("{INDENT}pub fn new(\n").as_str());
+        for param in &params {
+            out.push_str(format!("{INDENT}{INDENT}{},\n", param).as_str());
+        }
+        out.push_str(format!("{INDENT}) -> {} {{\n", ret_type).as_str());
+    }
+
+    // create object
+    let fields = names.join(", ");
+    if fields.len() <= STRUCT_LIT_WIDTH {
+        out.push_str(format!("{INDENT}{INDENT}{} {{ {} }}\n", ret_type, fields).as_str());
+    } else {
+        out.push_str(format!("{INDENT}{INDENT}{} {{\n", ret_type).as_str());
+        for name in &names {
+            out.push_str(format!("{INDENT}{INDENT}{INDENT}{},\n", name).as_str());
+        }
+        out.push_str(format!("{INDENT}{INDENT}}}\n").as_str());
+    }
+    out.push_str(format!("{INDENT}}}\n\n").as_str());
+    out
+}
+
+// an arena backed version of the same tree, nodes live in one flat
+// `<Base>Arena` and refer to their children by `<Base>Ref` index
+// instead of through a `Box`, which keeps large trees to a handful of
+// allocations and lays nodes out next to each other in memory
+pub fn define_arena_ast(tree: &Tree, names: &HashSet<&str>) -> String {
+    let base_name = tree.name.as_str();
+    let arena_types: Vec<TreeType> = tree.nodes.iter().map(|t| t.in_arena()).collect();
+    let arena = format!("{}Arena", base_name);
+    let node_ref = format!("{}Ref", base_name);
+
+    let mut out = file_header(tree, names, false);
+
+    // handle type and the arena itself
+    out.push_str(format!("// handle to a node stored in an {}\n", arena).as_str());
+    out.push_str("#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]\n");
+    out.push_str(format!("pub struct {}(u32);\n\n", node_ref).as_str());
+    out.push_str(format!("impl {} {{\n", node_ref).as_str());
+    out.push_str(format!("{INDENT}pub fn index(self) -> usize {{\n").as_str());
+    out.push_str(format!("{INDENT}{INDENT}self.0 as usize\n{INDENT}}}\n}}\n\n").as_str());
+
+    out.push_str("#[derive(Debug, Clone, Default)]\n");
+    out.push_str(format!("pub struct {} {{\n", arena).as_str());
+    out.push_str(format!("{INDENT}nodes: Vec<{}>,\n}}\n\n", base_name).as_str());
+    out.push_str(format!("impl {} {{\n", arena).as_str());
+    out.push_str(format!("{INDENT}pub fn new() -> {} {{\n", arena).as_str());
+    out.push_str(format!("{INDENT}{INDENT}{}::default()\n{INDENT}}}\n\n", arena).as_str());
+    out.push_str(
+        format!(
+            "{INDENT}pub fn with_capacity(capacity: usize) -> {} {{\n",
+            arena
+        )
+        .as_str(),
+    );
+    out.push_str(format!("{INDENT}{INDENT}{} {{\n", arena).as_str());
+    out.push_str(
+        format!("{INDENT}{INDENT}{INDENT}nodes: Vec::with_capacity(capacity),\n").as_str(),
+    );
+    out.push_str(format!("{INDENT}{INDENT}}}\n{INDENT}}}\n\n").as_str());
+    out.push_str(
+        format!(
+            "{INDENT}pub fn alloc(&mut self, node: {}) -> {} {{\n",
+            base_name, node_ref
+        )
+        .as_str(),
+    );
+    out.push_str(format!("{INDENT}{INDENT}self.nodes.push(node);\n").as_str());
+    out.push_str(
+        format!(
+            "{INDENT}{INDENT}{}((self.nodes.len() - 1) as u32)\n{INDENT}}}\n\n",
+            node_ref
+        )
+        .as_str(),
+    );
+    out.push_str(
+        format!(
+            "{INDENT}pub fn get(&self, node: {}) -> &{} {{\n",
+            node_ref, base_name
+        )
+        .as_str(),
+    );
+    out.push_str(format!("{INDENT}{INDENT}&self.nodes[node.index()]\n{INDENT}}}\n\n").as_str());
+    out.push_str(format!("{INDENT}pub fn len(&self) -> usize {{\n").as_str());
+    out.push_str(format!("{INDENT}{INDENT}self.nodes.len()\n{INDENT}}}\n\n").as_str());
+    out.push_str(format!("{INDENT}pub fn is_empty(&self) -> bool {{\n").as_str());
+    out.push_str(format!("{INDENT}{INDENT}self.nodes.is_empty()\n{INDENT}}}\n\n").as_str());
+    out.push_str(
+        format!(
+            "{INDENT}pub fn walk_{}<T>(&self, node: {}, v: &dyn {}Visitor<T>) -> T {{\n",
+            base_name.to_lowercase(),
+            node_ref,
+            base_name
+        )
+        .as_str(),
+    );
+    out.push_str(
+        format!(
+            "{INDENT}{INDENT}self.get(node).walk_{}(self, v)\n{INDENT}}}\n}}\n\n",
+            base_name.to_lowercase()
+        )
+        .as_str(),
+    );
+    out.push_str(format!("impl std::ops::Index<{}> for {} {{\n", node_ref, arena).as_str());
+    out.push_str(format!("{INDENT}type Output = {};\n\n", base_name).as_str());
+    out.push_str(
+        format!(
+            "{INDENT}fn index(&self, node: {}) -> &{} {{\n",
+            node_ref, base_name
+        )
+        .as_str(),
+    );
+    out.push_str(format!("{INDENT}{INDENT}self.get(node)\n{INDENT}}}\n}}\n\n").as_str());
+
+    out.push_str(define_base_enum(base_name, &arena_types).as_str());
+    for tree_type in arena_types.iter() {
+        out.push_str(tree_type.to_struct().as_str());
+    }
+
+    // visitor trait, visits are handed the arena so they can follow refs
+    out.push_str(format!("pub trait {}Visitor<T> {{\n", base_name).as_str());
+    for ttype in arena_types.iter() {
+        out.push_str(
+            format!(
+                "{INDENT}fn visit_{}(&self, arena: &{}, e: &{}) -> T;\n",
+                ttype.snake_case_full_name(),
+                arena,
+                ttype.full_name()
+            )
+            .as_str(),
+        );
+    }
+    out.push_str("}\n\n");
+
+    out.push_str(format!("impl {} {{\n", base_name).as_str());
+    out.push_str(
+        format!(
+            "{INDENT}pub fn walk_{}<T>(&self, arena: &{}, v: &dyn {}Visitor<T>) -> T {{\n",
+            base_name.to_lowercase(),
+            arena,
+            base_name
+        )
+        .as_str(),
+    );
+    out.push_str(format!("{INDENT}{INDENT}match self {{\n").as_str());
+    for ttype in arena_types.iter() {
+        out.push_str(
+            format!(
+                "{INDENT}{INDENT}{INDENT}{}::{}(e) => e.walk_{}(arena, v),\n",
+                base_name,
+                ttype.class_name,
+                ttype.snake_case_full_name()
+            )
+            .as_str(),
+        );
+    }
+    out.push_str(format!("{INDENT}{INDENT}}}\n{INDENT}}}\n").as_str());
+    out.push_str(define_node_accessors(base_name, &arena_types).as_str());
+    out.push_str("}\n\n");
+
+    for ttype in arena_types.iter() {
+        out.push_str(format!("impl {} {{\n", ttype.full_name()).as_str());
+        out.push_str(define_new_func_for_type(ttype).as_str());
+        out.push_str(
+            format!(
+                "{INDENT}pub fn walk_{}<T>(&self, arena: &{}, v: &dyn {}Visitor<T>) -> T {{\n",
+                ttype.snake_case_full_name(),
+                arena,
+                base_name,
+            )
+            .as_str(),
+        );
+        out.push_str(
+            format!(
+                "{INDENT}{INDENT}v.visit_{}(arena, self)\n",
+                ttype.snake_case_full_name()
+            )
+            .as_str(),
+        );
+        out.push_str(format!("{INDENT}}}\n}}\n\n").as_str());
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::file_header;
+    use crate::schema::parse_schema;
+
+    #[test]
+    fn imports_are_sorted_like_rustfmt() {
+        let trees = parse_schema(
+            "tree Stmt\n  Print : Box<Expr> value\n  Block : Vec<Decl> decls, Vec<Stmt> body\n\
+             tree Expr\n  Literal : Literal value\n\
+             tree Decl\n  Var : Token name, Option<Box<Expr>> init\n",
+        )
+        .unwrap();
+        let names: HashSet<&str> = trees.iter().map(|tree| tree.name.as_str()).collect();
+        assert_eq!(
+            file_header(&trees[0], &names, true),
+            "// generated by define_ast from ast.schema, do not edit by hand\n\
+             \n\
+             use std::hash::{Hash, Hasher};\n\
+             \n\
+             pub use crate::lexer::token::{Literal, Span, Token};\n\
+             use crate::syntax::decl::Decl;\n\
+             use crate::syntax::expr::Expr;\n\
+             pub use crate::syntax::NodeId;\n\
+             use crate::syntax::Structural;\n\
+             \n"
+        );
+        assert_eq!(
+            file_header(&trees[1], &names, false),
+            "// generated by define_ast from ast.schema, do not edit by hand\n\
+             \n\
+             pub use crate::lexer::token::{Literal, Span, Token};\n\
+             pub use crate::syntax::NodeId;\n\
+             \n"
+        );
+    }
+}
@@ -0,0 +1,207 @@
+// generates the syntax tree modules in src/syntax from the node types
+// declared in `<dir>/ast.schema`, which looks like
+//
+//     # comment
+//     tree Expr arena
+//         Binary : Box<Expr> left, Token operator, Box<Expr> right
+//         Call   : Box<Expr> callee, Vec<Expr> arguments
+//
+//     tree Stmt
+//         Return : Token keyword, Option<Box<Expr>> value
+//
+// each tree becomes `<tree>.rs` in the same directory, and trees marked
+// `arena` also get an arena backed `arena_<tree>.rs`. this is used by the
+// define_ast binary, and by lox_interpreter's build script to check the
+// generated files haven't drifted from the schema
+mod diff;
+mod generate;
+mod schema;
+
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub use diff::unified_diff;
+pub use schema::SchemaError;
+
+pub const SCHEMA_FILE: &str = "ast.schema";
+
+#[derive(Debug)]
+pub enum Error {
+    Io(PathBuf, io::Error),
+    Schema(PathBuf, SchemaError),
+    // the generated files that don't match the schema, as unified diffs
+    // from what's on disk to what would be generated
+    OutOfDate(Vec<String>),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Io(path, why) => write!(f, "couldn't access {}: {}", path.display(), why),
+            Error::Schema(path, err) => write!(f, "{}:{}", path.display(), err),
+            Error::OutOfDate(diffs) => {
+                for diff in diffs {
+                    write!(f, "{}", diff)?;
+                }
+                write!(
+                    f,
+                    "generated syntax trees are out of date with {}, regenerate them with \
+                     `cargo run -p define_ast -- <dir>`",
+                    SCHEMA_FILE
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+// every file the schema in `dir` generates, as (path, contents)
+pub fn generate(dir: &Path) -> Result<Vec<(PathBuf, String)>, Error> {
+    let schema_path = dir.join(SCHEMA_FILE);
+    let schema =
+        fs::read_to_string(&schema_path).map_err(|why| Error::Io(schema_path.clone(), why))?;
+    let trees = schema::parse_schema(&schema).map_err(|err| Error::Schema(schema_path, err))?;
+    let names: HashSet<&str> = trees.iter().map(|tree| tree.name.as_str()).collect();
+    let mut files = Vec::new();
+    for tree in &trees {
+        for (file_name, out) in generate::define_ast(tree, &names) {
+            // rustfmt wants exactly one newline at the end of the file
+            files.push((dir.join(file_name), format!("{}\n", out.trim_end())));
+        }
+    }
+    Ok(files)
+}
+
+// regenerate the files for the schema in `dir`, returning their paths
+pub fn write(dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    let mut written = Vec::new();
+    for (path, out) in generate(dir)? {
+        fs::write(&path, out).map_err(|why| Error::Io(path.clone(), why))?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+// make sure the files in `dir` are what the schema generates, without
+// touching them. they're compared line by line like the diff, so a
+// checkout with `\r\n` line endings or without the newline at the end
+// isn't out of date. a missing file counts as empty
+pub fn check(dir: &Path) -> Result<(), Error> {
+    let mut diffs = Vec::new();
+    for (path, expected) in generate(dir)? {
+        let actual = match fs::read_to_string(&path) {
+            Ok(actual) => actual,
+            Err(why) if why.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(why) => return Err(Error::Io(path, why)),
+        };
+        let name = path.display().to_string();
+        let diff = unified_diff(&actual, &expected, &name, &name);
+        if !diff.is_empty() {
+            diffs.push(diff);
+        }
+    }
+    match diffs.is_empty() {
+        true => Ok(()),
+        false => Err(Error::OutOfDate(diffs)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check, write, Error, SCHEMA_FILE};
+    use std::fs;
+    use std::path::PathBuf;
+
+    // a fresh directory with a schema in it, removed again when dropped
+    struct SchemaDir(PathBuf);
+
+    impl SchemaDir {
+        fn new(name: &str, schema: &str) -> SchemaDir {
+            let dir =
+                std::env::temp_dir().join(format!("define_ast_{}_{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join(SCHEMA_FILE), schema).unwrap();
+            SchemaDir(dir)
+        }
+
+        fn generated(&self) -> PathBuf {
+            self.0.join("expr.rs")
+        }
+    }
+
+    impl Drop for SchemaDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    const SCHEMA: &str =
+        "tree Expr\n    Literal : Literal value\n    Unary : Token operator, Box<Expr> right\n";
+
+    fn out_of_date(dir: &SchemaDir) -> Vec<String> {
+        match check(&dir.0) {
+            Err(Error::OutOfDate(diffs)) => diffs,
+            other => panic!("expected the files to be out of date, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn written_files_are_up_to_date() {
+        let dir = SchemaDir::new("written", SCHEMA);
+        assert_eq!(write(&dir.0).unwrap(), vec![dir.generated()]);
+        check(&dir.0).unwrap();
+    }
+
+    #[test]
+    fn line_endings_and_the_final_newline_dont_matter() {
+        let dir = SchemaDir::new("line_endings", SCHEMA);
+        write(&dir.0).unwrap();
+        let generated = fs::read_to_string(dir.generated()).unwrap();
+        fs::write(dir.generated(), generated.replace('\n', "\r\n")).unwrap();
+        check(&dir.0).unwrap();
+        fs::write(dir.generated(), generated.trim_end()).unwrap();
+        check(&dir.0).unwrap();
+    }
+
+    #[test]
+    fn edits_are_out_of_date_with_a_diff() {
+        let dir = SchemaDir::new("edited", SCHEMA);
+        write(&dir.0).unwrap();
+        let generated = fs::read_to_string(dir.generated()).unwrap();
+        fs::write(
+            dir.generated(),
+            generated.replace("pub value", "pub edited"),
+        )
+        .unwrap();
+        let diffs = out_of_date(&dir);
+        assert_eq!(diffs.len(), 1);
+        assert!(diffs[0].contains("\n-    pub edited: Literal,\n+    pub value: Literal,\n"));
+    }
+
+    #[test]
+    fn missing_files_are_out_of_date() {
+        let dir = SchemaDir::new("missing", SCHEMA);
+        let diffs = out_of_date(&dir);
+        assert_eq!(diffs.len(), 1);
+        assert!(diffs[0].contains("@@ -0,0 +1,"));
+    }
+
+    #[test]
+    fn schema_errors_name_the_schema() {
+        let dir = SchemaDir::new("bad_schema", "tree Expr\n    literal : Literal value\n");
+        match check(&dir.0) {
+            Err(err @ Error::Schema(..)) => assert_eq!(
+                err.to_string(),
+                format!(
+                    "{}:2: `literal` isn't a valid node name",
+                    dir.0.join(SCHEMA_FILE).display()
+                )
+            ),
+            other => panic!("expected a schema error, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,50 @@
+use std::env;
+use std::path::Path;
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().collect();
+    let (check, output_dir) = match args.as_slice() {
+        [_, dir] => (false, dir),
+        [_, flag, dir] if flag == "--check" => (true, dir),
+        _ => {
+            println!("Usage: define_ast [--check] <output directory>");
+            return ExitCode::FAILURE;
+        }
+    };
+    let output_dir = Path::new(output_dir);
+
+    if check {
+        // the diffs go to stdout so they can be piped into patch
+        return match define_ast::check(output_dir) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(define_ast::Error::OutOfDate(diffs)) => {
+                for diff in diffs {
+                    print!("{}", diff);
+                }
+                eprintln!(
+                    "error: generated files are out of date, run `define_ast {}`",
+                    output_dir.display()
+                );
+                ExitCode::FAILURE
+            }
+            Err(err) => {
+                eprintln!("error: {}", err);
+                ExitCode::FAILURE
+            }
+        };
+    }
+
+    match define_ast::write(output_dir) {
+        Ok(written) => {
+            for path in written {
+                println!("successfully wrote to {}", path.display());
+            }
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("error: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
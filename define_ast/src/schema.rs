@@ -0,0 +1,448 @@
+use std::collections::HashSet;
+
+// a problem with the schema, and the line it's on
+#[derive(Debug)]
+pub struct SchemaError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.line, self.message)
+    }
+}
+
+fn schema_error<T>(line: usize, message: String) -> Result<T, SchemaError> {
+    Err(SchemaError { line, message })
+}
+
+pub struct Tree {
+    // where the tree is declared in the schema
+    pub(crate) line: usize,
+    pub(crate) name: String,
+    pub(crate) arena: bool,
+    pub(crate) nodes: Vec<TreeType>,
+}
+
+pub(crate) fn parse_schema(schema: &str) -> Result<Vec<Tree>, SchemaError> {
+    let mut trees: Vec<Tree> = Vec::new();
+    for (i, line) in schema.lines().enumerate() {
+        let line_no = i + 1;
+        let line = match line.split_once('#') {
+            Some((before, _)) => before.trim(),
+            None => line.trim(),
+        };
+        if line.is_empty() {
+            continue;
+        }
+        // `tree` on its own is a header missing its name, not a node
+        if let Some(rest) = line
+            .strip_prefix("tree")
+            .filter(|rest| rest.is_empty() || rest.starts_with(char::is_whitespace))
+        {
+            trees.push(parse_tree_header(line_no, rest)?);
+            continue;
+        }
+        let tree = match trees.last_mut() {
+            Some(tree) => tree,
+            None => {
+                return schema_error(
+                    line_no,
+                    "node declared before any `tree <Name>` line".to_string(),
+                )
+            }
+        };
+        let node = parse_node(line_no, &tree.name, line)?;
+        if tree.nodes.iter().any(|n| n.class_name == node.class_name) {
+            return schema_error(
+                line_no,
+                format!("`{}` is declared twice in `{}`", node.class_name, tree.name),
+            );
+        }
+        tree.nodes.push(node);
+    }
+
+    let mut names = HashSet::new();
+    for tree in &trees {
+        if !names.insert(tree.name.as_str()) {
+            return schema_error(tree.line, format!("tree `{}` is declared twice", tree.name));
+        }
+        if tree.nodes.is_empty() {
+            return schema_error(tree.line, format!("tree `{}` has no nodes", tree.name));
+        }
+    }
+    for tree in &trees {
+        check_tree_references(tree, &names)?;
+    }
+    Ok(trees)
+}
+
+fn parse_tree_header(line: usize, header: &str) -> Result<Tree, SchemaError> {
+    let mut words = header.split_whitespace();
+    let name = match words.next() {
+        Some(name) if is_type_name(name) => name.to_string(),
+        Some(name) => return schema_error(line, format!("`{}` isn't a valid tree name", name)),
+        None => return schema_error(line, "expected a name after `tree`".to_string()),
+    };
+    let arena = match words.next() {
+        None => false,
+        Some("arena") => true,
+        Some(word) => {
+            return schema_error(
+                line,
+                format!(
+                    "unexpected `{}` after the tree name, expected `arena`",
+                    word
+                ),
+            )
+        }
+    };
+    if let Some(word) = words.next() {
+        return schema_error(line, format!("unexpected `{}` after `arena`", word));
+    }
+    Ok(Tree {
+        line,
+        name,
+        arena,
+        nodes: Vec::new(),
+    })
+}
+
+// `Binary : Box<Expr> left, Token operator, Box<Expr> right`
+fn parse_node(line: usize, base_name: &str, decl: &str) -> Result<TreeType, SchemaError> {
+    let (class_name, raw_fields) = match decl.split_once(':') {
+        Some((class_name, raw_fields)) => (class_name.trim(), raw_fields.trim()),
+        None => {
+            return schema_error(
+                line,
+                format!("expected `Name : fields` but found `{}`", decl),
+            )
+        }
+    };
+    if !is_type_name(class_name) {
+        return schema_error(line, format!("`{}` isn't a valid node name", class_name));
+    }
+    let mut fields: Vec<Field> = Vec::new();
+    if !raw_fields.is_empty() {
+        for raw_field in split_fields(raw_fields) {
+            let field = parse_field(line, raw_field.trim())?;
+            if fields.iter().any(|f| f.name == field.name) {
+                return schema_error(
+                    line,
+                    format!(
+                        "field `{}` is declared twice in `{}`",
+                        field.name, class_name
+                    ),
+                );
+            }
+            fields.push(field);
+        }
+    }
+    Ok(TreeType {
+        line,
+        base_name: base_name.to_string(),
+        class_name: class_name.to_string(),
+        fields,
+    })
+}
+
+// split on the commas between fields, not the ones inside `<..>`
+fn split_fields(raw_fields: &str) -> Vec<&str> {
+    let mut fields = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in raw_fields.char_indices() {
+        match c {
+            '<' => depth += 1,
+            '>' => depth -= 1,
+            ',' if depth == 0 => {
+                fields.push(&raw_fields[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    fields.push(&raw_fields[start..]);
+    fields
+}
+
+// `Option<Box<Expr>> value`
+fn parse_field(line: usize, raw_field: &str) -> Result<Field, SchemaError> {
+    let (field_type, name) = match raw_field.rsplit_once(char::is_whitespace) {
+        Some((field_type, name)) => (field_type.trim(), name),
+        None => {
+            return schema_error(
+                line,
+                format!("expected `Type name` but found `{}`", raw_field),
+            )
+        }
+    };
+    if !is_field_name(name) {
+        return schema_error(line, format!("`{}` isn't a valid field name", name));
+    }
+    if name == "id" || name == "span" {
+        return schema_error(
+            line,
+            format!("`{}` is added to every node, it can't be declared", name),
+        );
+    }
+    let ty = match parse_field_type(field_type) {
+        Some(ty) => ty,
+        None => return schema_error(line, format!("`{}` isn't a valid field type", field_type)),
+    };
+    Ok(Field {
+        name: name.to_string(),
+        ty,
+    })
+}
+
+fn parse_field_type(field_type: &str) -> Option<FieldType> {
+    let field_type = field_type.trim();
+    if let Some((wrapper, inner)) = field_type.split_once('<') {
+        let inner = parse_field_type(inner.strip_suffix('>')?)?;
+        return match wrapper.trim() {
+            "Box" => Some(FieldType::Boxed(Box::new(inner))),
+            "Option" => Some(FieldType::Option(Box::new(inner))),
+            "Vec" => Some(FieldType::List(Box::new(inner))),
+            _ => None,
+        };
+    }
+    match is_type_name(field_type) {
+        true => Some(FieldType::Named(field_type.to_string())),
+        false => None,
+    }
+}
+
+fn is_type_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_uppercase())
+        && name.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+fn is_field_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_lowercase() || c == '_')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+}
+
+// a tree can hold other trees, but only through a `Box` or a `Vec`, and
+// an arena tree only holds nodes of its own tree since they have to live
+// in its arena
+fn check_tree_references(tree: &Tree, names: &HashSet<&str>) -> Result<(), SchemaError> {
+    for node in &tree.nodes {
+        for field in &node.fields {
+            for referenced in field.ty.trees(names) {
+                if tree.arena && referenced != tree.name {
+                    return schema_error(
+                        node.line,
+                        format!(
+                            "`{}.{}` refers to `{}`, but arena tree `{}` can only hold its own nodes",
+                            node.class_name,
+                            field.name,
+                            referenced,
+                            tree.name
+                        ),
+                    );
+                }
+            }
+            if field.ty.is_unboxed_tree(names) {
+                return schema_error(
+                    node.line,
+                    format!(
+                        "`{}.{}` holds a tree directly, put it in a `Box` or a `Vec`",
+                        node.class_name, field.name
+                    ),
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+#[derive(Clone)]
+pub enum FieldType {
+    Boxed(Box<FieldType>),
+    Option(Box<FieldType>),
+    List(Box<FieldType>),
+    // anything else, like `Token` or the `Expr` in `Vec<Expr>`
+    Named(String),
+}
+
+impl FieldType {
+    // the trees a field refers to
+    pub(crate) fn trees<'a>(&'a self, names: &HashSet<&str>) -> Vec<&'a str> {
+        match self {
+            FieldType::Boxed(inner) | FieldType::Option(inner) | FieldType::List(inner) => {
+                inner.trees(names)
+            }
+            FieldType::Named(name) if names.contains(name.as_str()) => vec![name.as_str()],
+            FieldType::Named(_) => Vec::new(),
+        }
+    }
+
+    // a tree stored inline, or in an Option, would make the type infinitely
+    // sized when it refers back to itself
+    fn is_unboxed_tree(&self, names: &HashSet<&str>) -> bool {
+        match self {
+            FieldType::Option(inner) => inner.is_unboxed_tree(names),
+            FieldType::Named(name) => names.contains(name.as_str()),
+            FieldType::Boxed(_) | FieldType::List(_) => false,
+        }
+    }
+
+    // children are stored by handle in an arena, `Box<Expr>` and the
+    // `Expr` in `Vec<Expr>` both become `ExprRef`
+    pub(crate) fn in_arena(&self, base_name: &str) -> FieldType {
+        match self {
+            FieldType::Boxed(inner) => match inner.as_ref() {
+                FieldType::Named(name) if name == base_name => {
+                    FieldType::Named(format!("{}Ref", base_name))
+                }
+                _ => FieldType::Boxed(Box::new(inner.in_arena(base_name))),
+            },
+            FieldType::Option(inner) => FieldType::Option(Box::new(inner.in_arena(base_name))),
+            FieldType::List(inner) => FieldType::List(Box::new(inner.in_arena(base_name))),
+            FieldType::Named(name) if name == base_name => {
+                FieldType::Named(format!("{}Ref", base_name))
+            }
+            FieldType::Named(name) => FieldType::Named(name.clone()),
+        }
+    }
+}
+
+impl std::fmt::Display for FieldType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FieldType::Boxed(inner) => write!(f, "Box<{}>", inner),
+            FieldType::Option(inner) => write!(f, "Option<{}>", inner),
+            FieldType::List(inner) => write!(f, "Vec<{}>", inner),
+            FieldType::Named(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Field {
+    pub(crate) name: String,
+    pub(crate) ty: FieldType,
+}
+
+pub struct TreeType {
+    pub(crate) line: usize,
+    pub(crate) base_name: String,
+    pub(crate) class_name: String,
+    pub(crate) fields: Vec<Field>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_schema;
+
+    // the error for a schema, as `line: message`
+    fn error(schema: &str) -> String {
+        match parse_schema(schema) {
+            Ok(_) => panic!("expected an error for {:?}", schema),
+            Err(err) => err.to_string(),
+        }
+    }
+
+    #[test]
+    fn valid_schemas() {
+        let trees = parse_schema(
+            "# comment\ntree Expr arena # trailing\n    Literal : Literal value\n\
+             \n    Call : Box<Expr> callee, Vec<Expr> arguments\n\
+             tree Stmt\n    Return : Token keyword, Option<Box<Expr>> value\n    Empty :\n",
+        )
+        .unwrap();
+        let shape: Vec<(&str, bool, usize)> = trees
+            .iter()
+            .map(|tree| (tree.name.as_str(), tree.arena, tree.nodes.len()))
+            .collect();
+        assert_eq!(shape, vec![("Expr", true, 2), ("Stmt", false, 2)]);
+    }
+
+    #[test]
+    fn tree_errors() {
+        let cases = [
+            ("tree\n", "1: expected a name after `tree`"),
+            ("tree expr\n", "1: `expr` isn't a valid tree name"),
+            (
+                "tree Expr boxed\n",
+                "1: unexpected `boxed` after the tree name, expected `arena`",
+            ),
+            ("tree Expr arena now\n", "1: unexpected `now` after `arena`"),
+            (
+                "\n    Literal : Literal value\n",
+                "2: node declared before any `tree <Name>` line",
+            ),
+            (
+                "tree Expr\n\ntree Stmt\n  A : Token a\n",
+                "1: tree `Expr` has no nodes",
+            ),
+            (
+                "tree Expr\n  A : Token a\ntree Expr\n  B : Token b\n",
+                "3: tree `Expr` is declared twice",
+            ),
+        ];
+        for (schema, expected) in cases {
+            assert_eq!(error(schema), expected);
+        }
+    }
+
+    #[test]
+    fn node_errors() {
+        let cases = [
+            (
+                "Literal Literal value",
+                "2: expected `Name : fields` but found `Literal Literal value`",
+            ),
+            (
+                "literal : Literal value",
+                "2: `literal` isn't a valid node name",
+            ),
+            (
+                "A : Token a\n  A : Token b",
+                "3: `A` is declared twice in `Expr`",
+            ),
+            (
+                "A : Token a, Token a",
+                "2: field `a` is declared twice in `A`",
+            ),
+            ("A : Token", "2: expected `Type name` but found `Token`"),
+            ("A : Token Name", "2: `Name` isn't a valid field name"),
+            (
+                "A : Token id",
+                "2: `id` is added to every node, it can't be declared",
+            ),
+            (
+                "A : Span span",
+                "2: `span` is added to every node, it can't be declared",
+            ),
+            ("A : Rc<Token> a", "2: `Rc<Token>` isn't a valid field type"),
+            (
+                "A : Box<token> a",
+                "2: `Box<token>` isn't a valid field type",
+            ),
+            (
+                "A : Expr inner",
+                "2: `A.inner` holds a tree directly, put it in a `Box` or a `Vec`",
+            ),
+            (
+                "A : Option<Expr> inner",
+                "2: `A.inner` holds a tree directly, put it in a `Box` or a `Vec`",
+            ),
+        ];
+        for (node, expected) in cases {
+            assert_eq!(error(&format!("tree Expr\n  {}\n", node)), expected);
+        }
+    }
+
+    #[test]
+    fn arena_trees_only_hold_their_own_nodes() {
+        assert_eq!(
+            error("tree Expr arena\n  A : Box<Stmt> s\ntree Stmt\n  B : Token b\n"),
+            "2: `A.s` refers to `Stmt`, but arena tree `Expr` can only hold its own nodes"
+        );
+    }
+}
@@ -0,0 +1,14 @@
+use crate::lexer::token::Literal;
+use crate::syntax::expr::*;
+
+// the literal an expression always evaluates to, looking through parens.
+// the lint, the type checker and the folder all go by this so they agree
+// on what is constant. only literals count, an operator on literals is
+// constant too but the folder is what works those out
+pub fn constant(e: &Expr) -> Option<&Literal> {
+    match e {
+        Expr::Literal(l) => Some(&l.value),
+        Expr::Grouping(g) => constant(&g.expression),
+        _ => None,
+    }
+}
@@ -0,0 +1,282 @@
+use std::collections::HashMap;
+
+use crate::analysis::constant;
+use crate::error_handling::{Diagnostic, Severity};
+use crate::lexer::token::{Token, TokenKind};
+use crate::syntax::expr::*;
+
+// lints warn about code that is legal but almost certainly not what was
+// meant. lox only has expressions so far, so this is all we can check;
+// unused variables, shadowing, unreachable code after `return` and self
+// assignment belong here too once the parser knows about declarations
+// and statements
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Lint {
+    ConstantCondition,
+}
+
+impl Lint {
+    pub const ALL: &'static [Lint] = &[Lint::ConstantCondition];
+
+    // the name used to refer to the lint in directives
+    pub fn name(&self) -> &'static str {
+        match self {
+            Lint::ConstantCondition => "constant-condition",
+        }
+    }
+
+    // warnings get their own W codes, W00xx for problems with the lint
+    // directives themselves and W01xx for the lints
+    pub fn code(&self) -> &'static str {
+        match self {
+            Lint::ConstantCondition => "W0101",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Lint> {
+        Lint::ALL.iter().copied().find(|lint| lint.name() == name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LintLevel {
+    Allow,
+    Warn,
+}
+
+const DIRECTIVE_CODE: &str = "W0001";
+
+// which lints are on for a file, every lint warns unless a directive
+// comment anywhere in the file says otherwise:
+//
+//     // lox: allow(constant-condition)
+//     // lox: warn(constant-condition)
+//
+// a later directive for the same lint wins
+#[derive(Debug, Clone, Default)]
+pub struct LintConfig {
+    levels: HashMap<Lint, LintLevel>,
+}
+
+impl LintConfig {
+    pub fn level(&self, lint: Lint) -> LintLevel {
+        self.levels.get(&lint).copied().unwrap_or(LintLevel::Warn)
+    }
+
+    pub fn set(&mut self, lint: Lint, level: LintLevel) {
+        self.levels.insert(lint, level);
+    }
+
+    // read the directives out of a file's comments, anything that looks
+    // like a directive but can't be understood is warned about and skipped
+    pub fn from_comments<'a>(
+        comments: impl IntoIterator<Item = &'a Token>,
+    ) -> (LintConfig, Vec<Diagnostic>) {
+        let mut config = LintConfig::default();
+        let mut warnings = Vec::new();
+        for comment in comments {
+            if comment.kind != TokenKind::Comment {
+                continue;
+            }
+            let directive = match comment
+                .lexeme
+                .trim_start_matches('/')
+                .trim()
+                .strip_prefix("lox:")
+            {
+                Some(directive) => directive.trim(),
+                None => continue,
+            };
+            let invalid = |message: String| {
+                Diagnostic::new(DIRECTIVE_CODE, Severity::Warning, message, comment.span)
+            };
+            let (level, names) = match parse_directive(directive) {
+                Some(parsed) => parsed,
+                None => {
+                    warnings.push(invalid(format!(
+                        "malformed lint directive `{}`, expected `allow(..)` or `warn(..)`",
+                        directive
+                    )));
+                    continue;
+                }
+            };
+            for name in names {
+                match Lint::from_name(name) {
+                    Some(lint) => config.set(lint, level),
+                    None => warnings.push(invalid(format!("unknown lint `{}`", name))),
+                }
+            }
+        }
+        (config, warnings)
+    }
+}
+
+// `allow(a, b)` -> (Allow, ["a", "b"])
+fn parse_directive(directive: &str) -> Option<(LintLevel, Vec<&str>)> {
+    let (level, rest) = directive.split_once('(')?;
+    let level = match level.trim() {
+        "allow" => LintLevel::Allow,
+        "warn" => LintLevel::Warn,
+        _ => return None,
+    };
+    let names = rest.trim_end().strip_suffix(')')?;
+    let names: Vec<&str> = names
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .collect();
+    Some((level, names))
+}
+
+pub struct Linter {
+    config: LintConfig,
+    warnings: Vec<Diagnostic>,
+}
+
+impl Linter {
+    pub fn new(config: LintConfig) -> Linter {
+        Linter {
+            config,
+            warnings: Vec::new(),
+        }
+    }
+
+    // every warning for the expression that its lint is enabled for,
+    // in source order
+    pub fn check(mut self, e: &Expr) -> Vec<Diagnostic> {
+        self.visit_expr(e);
+        self.warnings
+    }
+
+    fn warn(&mut self, lint: Lint, message: String, expr: &Expr) {
+        if self.config.level(lint) == LintLevel::Warn {
+            self.warnings.push(Diagnostic::new(
+                lint.code(),
+                Severity::Warning,
+                message,
+                expr.span(),
+            ));
+        }
+    }
+}
+
+impl ExprVisitorMut for Linter {
+    fn visit_conditional_expr(&mut self, c: &ConditionalExpr) {
+        if let Some(value) = constant(&c.condition) {
+            self.warn(
+                Lint::ConstantCondition,
+                format!("condition is always {}", value.is_truthy()),
+                &c.condition,
+            );
+        }
+        walk_conditional_expr_mut(self, c);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Lint, LintConfig, LintLevel, Linter};
+    use crate::lexer::token::Token;
+    use crate::lexer::{is_comment, scan_tokens_with_comments};
+    use crate::parser::parse;
+
+    // every warning for the source, directives first then lints, as
+    // (code, message, line)
+    fn lint(source: &str) -> Vec<(&'static str, String, u32)> {
+        let (comments, tokens): (Vec<Token>, Vec<Token>) =
+            scan_tokens_with_comments(source).partition(is_comment);
+        let expr = parse(tokens).unwrap();
+        let (config, mut warnings) = LintConfig::from_comments(&comments);
+        warnings.extend(Linter::new(config).check(&expr));
+        warnings
+            .into_iter()
+            .map(|warning| (warning.code, warning.message, warning.line))
+            .collect()
+    }
+
+    fn level(source: &str) -> LintLevel {
+        let comments: Vec<Token> = scan_tokens_with_comments(source)
+            .filter(is_comment)
+            .collect();
+        LintConfig::from_comments(&comments)
+            .0
+            .level(Lint::ConstantCondition)
+    }
+
+    #[test]
+    fn lints_warn_by_default() {
+        assert_eq!(level("1"), LintLevel::Warn);
+        assert_eq!(
+            level("// lox: warn(constant-condition)\n1"),
+            LintLevel::Warn
+        );
+        assert_eq!(
+            level("// lox: allow(constant-condition)\n1"),
+            LintLevel::Allow
+        );
+        // only line comments hold directives
+        assert_eq!(
+            level("/* lox: allow(constant-condition) */ 1"),
+            LintLevel::Warn
+        );
+    }
+
+    #[test]
+    fn the_last_directive_wins() {
+        let source = "// lox: allow(constant-condition)\n1 // lox: warn(constant-condition)";
+        assert_eq!(level(source), LintLevel::Warn);
+        let source = "// lox: warn(constant-condition)\n// lox: allow(constant-condition, )\n1";
+        assert_eq!(level(source), LintLevel::Allow);
+    }
+
+    #[test]
+    fn bad_directives_are_warned_about() {
+        assert_eq!(
+            lint("// lox: deny(constant-condition)\n1"),
+            vec![(
+                "W0001",
+                "malformed lint directive `deny(constant-condition)`, expected `allow(..)` or `warn(..)`"
+                    .to_string(),
+                1
+            )]
+        );
+        assert_eq!(lint("// lox: allow(constant-condition\n1")[0].0, "W0001");
+        assert_eq!(
+            lint("1\n// lox: allow(constant-condition, no-such-lint)"),
+            vec![("W0001", "unknown lint `no-such-lint`".to_string(), 2)]
+        );
+        // the lints the directive could understand still apply
+        assert_eq!(
+            lint("// lox: allow(no-such-lint, constant-condition)\ntrue ? 1 : 2").len(),
+            1
+        );
+    }
+
+    #[test]
+    fn comments_that_arent_directives_are_ignored() {
+        assert_eq!(
+            lint("// allow(constant-condition)\n// lox is fun\n1"),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn constant_conditions() {
+        assert_eq!(
+            lint("1 < 2 ? (true ? 1 : 2) : nil ? 3 : 4"),
+            vec![
+                ("W0101", "condition is always true".to_string(), 1),
+                ("W0101", "condition is always false".to_string(), 1),
+            ]
+        );
+        assert_eq!(lint("((0)) ? 1 : 2")[0].1, "condition is always true");
+        assert_eq!(lint("(\"\") ? 1 : 2")[0].1, "condition is always true");
+        // only literals are constant, operators on them are left to the folder
+        assert_eq!(lint("1 < 2 ? 1 : 2"), vec![]);
+        assert_eq!(lint("!true ? 1 : 2"), vec![]);
+        assert_eq!(
+            lint("// lox: allow(constant-condition)\ntrue ? 1 : 2"),
+            vec![]
+        );
+    }
+}
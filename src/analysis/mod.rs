@@ -0,0 +1,7 @@
+// static checks over a parsed program, run by `check` without executing it
+mod constant;
+pub mod lint;
+pub mod types;
+pub use constant::constant;
+pub use lint::{Lint, LintConfig, LintLevel, Linter};
+pub use types::{Type, TypeChecker};
@@ -0,0 +1,295 @@
+use std::cell::RefCell;
+
+use crate::analysis::constant;
+use crate::error_handling::{RuntimeErrorKind, TypeError};
+use crate::lexer::token::{Literal, Span, TokenKind};
+use crate::syntax::expr::*;
+
+// what we can tell about a value without running the program. `Unknown`
+// means it could be anything, e.g. either branch of a conditional whose
+// condition isn't known
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Type {
+    Number,
+    String,
+    Bool,
+    Nil,
+    Unknown,
+}
+
+impl Type {
+    fn is_known(self) -> bool {
+        self != Type::Unknown
+    }
+}
+
+impl std::fmt::Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Type::Number => write!(f, "number"),
+            Type::String => write!(f, "string"),
+            Type::Bool => write!(f, "bool"),
+            Type::Nil => write!(f, "nil"),
+            Type::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+// infers the type of every expression and reports the operations that are
+// certain to throw a runtime error. anything that might work is let through,
+// this only catches what can't. once an operation has failed its type is
+// `Unknown`, so one mistake isn't reported again by every operator above it.
+// only the branch of a `?:` that runs can throw: with a literal condition
+// the other one is skipped, and without one neither is certain to run
+#[derive(Default)]
+pub struct TypeChecker {
+    errors: RefCell<Vec<TypeError>>,
+}
+
+impl TypeChecker {
+    pub fn new() -> TypeChecker {
+        TypeChecker::default()
+    }
+
+    // the type of the expression, along with every error found in it
+    pub fn check(&self, e: &Expr) -> (Type, Vec<TypeError>) {
+        let t = e.walk_expr(self);
+        (t, self.errors.take())
+    }
+
+    fn error(&self, kind: RuntimeErrorKind, found: String, span: Span) -> Type {
+        self.errors
+            .borrow_mut()
+            .push(TypeError::new(kind, found, span));
+        Type::Unknown
+    }
+
+    fn numbers(&self, b: &BinaryExpr, left: Type, right: Type, result: Type) -> Type {
+        let fails = |t: Type| t.is_known() && t != Type::Number;
+        if fails(left) || fails(right) {
+            return self.error(
+                RuntimeErrorKind::OperandsMustBeNumbers,
+                format!("{} and {}", left, right),
+                b.span,
+            );
+        }
+        result
+    }
+
+    // `+` adds two numbers or concatenates two strings
+    fn plus(&self, b: &BinaryExpr, left: Type, right: Type) -> Type {
+        let addable = |t: Type| matches!(t, Type::Number | Type::String | Type::Unknown);
+        let mismatched = left.is_known() && right.is_known() && left != right;
+        if !addable(left) || !addable(right) || mismatched {
+            return self.error(
+                RuntimeErrorKind::OperandsMustBeNumbersOrStrings,
+                format!("{} and {}", left, right),
+                b.span,
+            );
+        }
+        // if it works at all, the known side says what it gives back
+        if left.is_known() {
+            left
+        } else {
+            right
+        }
+    }
+}
+
+impl ExprVisitor<Type> for TypeChecker {
+    fn visit_binary_expr(&self, b: &BinaryExpr) -> Type {
+        let left = b.left.walk_expr(self);
+        let right = b.right.walk_expr(self);
+        match b.operator.kind {
+            TokenKind::Plus => self.plus(b, left, right),
+            TokenKind::Minus | TokenKind::Star | TokenKind::Slash => {
+                self.numbers(b, left, right, Type::Number)
+            }
+            TokenKind::Greater
+            | TokenKind::GreaterEqual
+            | TokenKind::Less
+            | TokenKind::LessEqual => self.numbers(b, left, right, Type::Bool),
+            TokenKind::EqualEqual | TokenKind::BangEqual => Type::Bool,
+            TokenKind::Comma => right,
+            _ => Type::Unknown,
+        }
+    }
+    fn visit_conditional_expr(&self, c: &ConditionalExpr) -> Type {
+        c.condition.walk_expr(self);
+        if let Some(condition) = constant(&c.condition) {
+            return if condition.is_truthy() {
+                c.then_branch.walk_expr(self)
+            } else {
+                c.else_branch.walk_expr(self)
+            };
+        }
+        // the errors found in either branch might never happen
+        let errors = self.errors.borrow().len();
+        let then_branch = c.then_branch.walk_expr(self);
+        let else_branch = c.else_branch.walk_expr(self);
+        self.errors.borrow_mut().truncate(errors);
+        if then_branch == else_branch {
+            then_branch
+        } else {
+            Type::Unknown
+        }
+    }
+    fn visit_grouping_expr(&self, g: &GroupingExpr) -> Type {
+        g.expression.walk_expr(self)
+    }
+    fn visit_literal_expr(&self, l: &LiteralExpr) -> Type {
+        match l.value {
+            Literal::Num { .. } => Type::Number,
+            Literal::Str { .. } => Type::String,
+            Literal::Bool { .. } => Type::Bool,
+            Literal::Nil => Type::Nil,
+        }
+    }
+    fn visit_unary_expr(&self, u: &UnaryExpr) -> Type {
+        let right = u.right.walk_expr(self);
+        match u.operator.kind {
+            TokenKind::Minus if right.is_known() && right != Type::Number => self.error(
+                RuntimeErrorKind::OperandMustBeNumber,
+                right.to_string(),
+                u.span,
+            ),
+            TokenKind::Minus => Type::Number,
+            TokenKind::Bang => Type::Bool,
+            _ => Type::Unknown,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Type, TypeChecker};
+    use crate::error_handling::RuntimeErrorKind;
+    use crate::lexer::scan_tokens;
+    use crate::parser::parse;
+
+    // the type of the source and the kinds of the errors found in it
+    fn check(source: &str) -> (Type, Vec<RuntimeErrorKind>) {
+        let (t, errors) = TypeChecker::new().check(&parse(scan_tokens(source)).unwrap());
+        (t, errors.into_iter().map(|error| error.kind).collect())
+    }
+
+    fn assert_type(source: &str, expected: Type) {
+        assert_eq!(check(source), (expected, vec![]), "{source}");
+    }
+
+    fn assert_error(source: &str, expected: RuntimeErrorKind) {
+        assert_eq!(check(source), (Type::Unknown, vec![expected]), "{source}");
+    }
+
+    // a condition that isn't a literal, so it could go either way
+    const UNKNOWN: &str = "(1 < 2 ? 1 : \"a\")";
+
+    #[test]
+    fn literals() {
+        assert_type("1", Type::Number);
+        assert_type("\"a\"", Type::String);
+        assert_type("true", Type::Bool);
+        assert_type("nil", Type::Nil);
+        assert_type("(((1)))", Type::Number);
+    }
+
+    #[test]
+    fn arithmetic_needs_numbers() {
+        for op in ["-", "*", "/"] {
+            assert_type(&format!("1 {op} 2"), Type::Number);
+            assert_error(
+                &format!("1 {op} \"a\""),
+                RuntimeErrorKind::OperandsMustBeNumbers,
+            );
+            assert_error(
+                &format!("nil {op} 1"),
+                RuntimeErrorKind::OperandsMustBeNumbers,
+            );
+        }
+    }
+
+    #[test]
+    fn comparisons_need_numbers() {
+        for op in [">", ">=", "<", "<="] {
+            assert_type(&format!("1 {op} 2"), Type::Bool);
+            assert_error(
+                &format!("true {op} 2"),
+                RuntimeErrorKind::OperandsMustBeNumbers,
+            );
+        }
+    }
+
+    #[test]
+    fn plus_adds_numbers_or_strings() {
+        assert_type("1 + 2", Type::Number);
+        assert_type("\"a\" + \"b\"", Type::String);
+        for source in ["1 + \"a\"", "\"a\" + 1", "true + true", "nil + 1"] {
+            assert_error(source, RuntimeErrorKind::OperandsMustBeNumbersOrStrings);
+        }
+    }
+
+    #[test]
+    fn equality_and_comma_never_fail() {
+        assert_type("1 == \"a\"", Type::Bool);
+        assert_type("nil != false", Type::Bool);
+        assert_type("1, \"a\"", Type::String);
+    }
+
+    #[test]
+    fn unary_operators() {
+        assert_type("-1", Type::Number);
+        assert_error("-\"a\"", RuntimeErrorKind::OperandMustBeNumber);
+        assert_type("!1", Type::Bool);
+        assert_type("!nil", Type::Bool);
+    }
+
+    #[test]
+    fn unknown_operands_are_let_through() {
+        assert_type(UNKNOWN, Type::Unknown);
+        assert_type(&format!("-{UNKNOWN}"), Type::Number);
+        assert_type(&format!("{UNKNOWN} * 2"), Type::Number);
+        assert_type(&format!("{UNKNOWN} < 2"), Type::Bool);
+        // whichever it is, the known side says what `+` gives back
+        assert_type(&format!("{UNKNOWN} + \"b\""), Type::String);
+        assert_type(&format!("1 + {UNKNOWN}"), Type::Number);
+        // but a side that can't be added fails whatever the other one is
+        assert_error(
+            &format!("{UNKNOWN} + nil"),
+            RuntimeErrorKind::OperandsMustBeNumbersOrStrings,
+        );
+    }
+
+    #[test]
+    fn errors_are_only_reported_once() {
+        assert_eq!(
+            check("-(1 - \"a\") * 2 + 3"),
+            (Type::Number, vec![RuntimeErrorKind::OperandsMustBeNumbers])
+        );
+    }
+
+    #[test]
+    fn conditionals_with_a_literal_condition_only_check_the_branch_taken() {
+        assert_type("false ? \"a\" - 1 : 2", Type::Number);
+        assert_type("(true) ? 2 : \"a\" - 1", Type::Number);
+        assert_type("nil ? 1 : \"a\"", Type::String);
+        assert_error(
+            "true ? \"a\" - 1 : 2",
+            RuntimeErrorKind::OperandsMustBeNumbers,
+        );
+        // 0 is truthy
+        assert_type("0 ? 2 : -nil", Type::Number);
+        assert_error("nil ? 2 : -nil", RuntimeErrorKind::OperandMustBeNumber);
+    }
+
+    #[test]
+    fn conditionals_with_an_unknown_condition_are_never_certain_to_fail() {
+        assert_type("1 < 2 ? \"a\" - 1 : 2", Type::Unknown);
+        assert_type("1 < 2 ? 1 : -\"a\"", Type::Unknown);
+        assert_type("1 < 2 ? 1 : 2", Type::Number);
+        // the condition itself always runs
+        assert_eq!(
+            check("-nil ? 1 : 2"),
+            (Type::Number, vec![RuntimeErrorKind::OperandMustBeNumber])
+        );
+    }
+}
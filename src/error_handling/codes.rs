@@ -0,0 +1,36 @@
+// extended explanations for every error and warning code we can report,
+// shown by `lox_interpreter explain <code>`. the text is embedded into
+// the binary so it is always available
+
+pub const CODES: &[(&str, &str)] = &[
+    ("E0003", include_str!("explanations/E0003.md")),
+    ("E0004", include_str!("explanations/E0004.md")),
+    ("E0005", include_str!("explanations/E0005.md")),
+    ("E0006", include_str!("explanations/E0006.md")),
+    ("E0101", include_str!("explanations/E0101.md")),
+    ("E0102", include_str!("explanations/E0102.md")),
+    ("E0103", include_str!("explanations/E0103.md")),
+    ("E0201", include_str!("explanations/E0201.md")),
+    ("E0202", include_str!("explanations/E0202.md")),
+    ("E0401", include_str!("explanations/E0401.md")),
+    ("E0402", include_str!("explanations/E0402.md")),
+    ("E0403", include_str!("explanations/E0403.md")),
+    ("W0001", include_str!("explanations/W0001.md")),
+    ("W0101", include_str!("explanations/W0101.md")),
+];
+
+// look up the explanation for a code, the leading `E` is optional
+// and case doesn't matter, so `e0101` and `0101` find `E0101` too.
+// warnings always need their `W`
+pub fn explain(code: &str) -> Option<&'static str> {
+    let code = code.to_uppercase();
+    let code = if code.starts_with('E') || code.starts_with('W') {
+        code
+    } else {
+        format!("E{code}")
+    };
+    CODES
+        .iter()
+        .find(|(c, _)| *c == code)
+        .map(|(_, explanation)| *explanation)
+}
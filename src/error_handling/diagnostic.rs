@@ -0,0 +1,150 @@
+use super::{codes, report_error_str, LoxError, StackTrace};
+use crate::json;
+use crate::lexer::token::Span;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+// a single message for the user about their program, in a shape that
+// can be written out for people or as json for tools
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub code: &'static str,
+    pub severity: Severity,
+    pub message: String,
+    pub span: Option<Span>,
+    // line to report when there's no span, 0 if there isn't one either
+    pub line: u32,
+    pub notes: Vec<String>,
+    // where a runtime error was raised from, empty for everything else
+    pub trace: StackTrace,
+}
+
+impl Diagnostic {
+    pub fn new(code: &'static str, severity: Severity, message: String, span: Span) -> Diagnostic {
+        Diagnostic {
+            code,
+            severity,
+            message,
+            span: Some(span),
+            line: span.line,
+            notes: explain_notes(code),
+            trace: StackTrace::new(),
+        }
+    }
+
+    pub fn note(mut self, note: String) -> Diagnostic {
+        self.notes.push(note);
+        self
+    }
+
+    // one json object per diagnostic. the field names and their order
+    // are part of our output format, tools parse this, so only ever add
+    // new fields at the end
+    pub fn to_json(&self, file: Option<&str>) -> String {
+        let (span, line, column) = match self.span {
+            Some(span) => (
+                format!("{{\"start\":{},\"end\":{}}}", span.start, span.end),
+                span.line.to_string(),
+                span.column.to_string(),
+            ),
+            None => match self.line {
+                0 => ("null".to_string(), "null".to_string(), "null".to_string()),
+                line => ("null".to_string(), line.to_string(), "null".to_string()),
+            },
+        };
+        let notes: Vec<String> = self.notes.iter().map(|n| json::quote(n)).collect();
+        // tools get every frame, only the human output collapses deep traces
+        let trace: Vec<String> = self
+            .trace
+            .frames()
+            .iter()
+            .map(|frame| {
+                format!(
+                    "{{\"function\":{},\"file\":{},\"line\":{}}}",
+                    json::quote(&frame.function),
+                    frame
+                        .file
+                        .as_deref()
+                        .map_or("null".to_string(), json::quote),
+                    frame.line
+                )
+            })
+            .collect();
+        format!(
+            "{{\"code\":{},\"severity\":{},\"message\":{},\"file\":{},\"span\":{},\"line\":{},\"column\":{},\"notes\":[{}],\"trace\":[{}]}}",
+            json::quote(self.code),
+            json::quote(&self.severity.to_string()),
+            json::quote(&self.message),
+            file.map_or("null".to_string(), json::quote),
+            span,
+            line,
+            column,
+            notes.join(","),
+            trace.join(",")
+        )
+    }
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let label = match self.severity {
+            Severity::Error => "Error",
+            Severity::Warning => "Warning",
+        };
+        match self.line {
+            // errors from outside the program, like a missing file
+            0 => write!(f, "{}[{}]: {}", label, self.code, self.message)?,
+            line => write!(
+                f,
+                "{}",
+                report_error_str(line, label, self.code, &self.message)
+            )?,
+        }
+        if !self.trace.is_empty() {
+            write!(f, "\n{}", self.trace)?;
+        }
+        Ok(())
+    }
+}
+
+impl From<&LoxError> for Diagnostic {
+    fn from(err: &LoxError) -> Diagnostic {
+        let notes = explain_notes(err.code());
+        Diagnostic {
+            code: err.code(),
+            severity: Severity::Error,
+            message: err.message(),
+            span: err.span(),
+            line: err.line(),
+            notes,
+            trace: match err {
+                LoxError::Runtime(e) => e.trace.clone(),
+                _ => StackTrace::new(),
+            },
+        }
+    }
+}
+
+// point at `explain` for codes that have a longer write up
+fn explain_notes(code: &str) -> Vec<String> {
+    match codes::explain(code) {
+        Some(_) => vec![format!(
+            "run `lox_interpreter explain {}` for more information",
+            code
+        )],
+        None => Vec::new(),
+    }
+}
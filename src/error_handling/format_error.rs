@@ -0,0 +1,44 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FormatErrorKind {
+    // `fmt --check` found source that formatting would change
+    Unformatted,
+    // the formatted source parses to a different tree, which is a bug
+    // in the formatter. the source is left alone when this happens
+    ChangedTree,
+}
+
+#[derive(Debug)]
+pub struct FormatError {
+    pub kind: FormatErrorKind,
+    // first line formatting changes, or 0 if we don't know
+    pub line: u32,
+}
+
+impl FormatError {
+    pub fn new(kind: FormatErrorKind, line: u32) -> FormatError {
+        FormatError { kind, line }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self.kind {
+            FormatErrorKind::Unformatted => "E0005",
+            FormatErrorKind::ChangedTree => "E0006",
+        }
+    }
+}
+
+impl std::fmt::Display for FormatError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.kind {
+            FormatErrorKind::Unformatted => {
+                write!(f, "Not formatted, run `lox_interpreter fmt` to fix it")
+            }
+            FormatErrorKind::ChangedTree => write!(
+                f,
+                "Formatting would change what the source means, please report this as a bug"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for FormatError {}
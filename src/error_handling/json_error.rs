@@ -0,0 +1,27 @@
+// a json document that couldn't be read, either because it isn't valid
+// json or because it doesn't have the shape we were expecting
+#[derive(Debug)]
+pub struct JsonError {
+    // line of the document the error is on, 0 for shape errors since
+    // those are found after parsing, when the line is gone
+    pub line: u32,
+    pub message: String,
+}
+
+impl JsonError {
+    pub fn new(line: u32, message: String) -> JsonError {
+        JsonError { line, message }
+    }
+
+    pub fn code(&self) -> &'static str {
+        "E0003"
+    }
+}
+
+impl std::fmt::Display for JsonError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for JsonError {}
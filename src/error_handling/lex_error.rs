@@ -1,29 +1,75 @@
-use crate::error_handling::{report_error_str, Error};
+use crate::lexer::token::{Literal, Span, Token, TokenKind};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LexErrorKind {
+    UnterminatedString,
+    UnexpectedCharacter,
+    UnterminatedBlockComment,
+}
 
 #[derive(Debug)]
 pub struct LexError {
+    pub kind: LexErrorKind,
     pub message: String,
-    pub line: u32,
+    pub span: Span,
 }
 
 impl LexError {
-    pub fn new(message: String, line: u32) -> LexError {
-        LexError { message, line }
+    pub fn new(kind: LexErrorKind, message: String, span: Span) -> LexError {
+        LexError {
+            kind,
+            message,
+            span,
+        }
     }
-}
 
-impl std::fmt::Display for LexError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", report_error_str(self.line, self.message.clone()))
+    // the lexer never fails outright, it hands back tokens that record
+    // what went wrong, so check whether this token is one of those
+    pub fn from_token(token: &Token) -> Option<LexError> {
+        let unterminated = matches!(
+            token.literal,
+            Some(Literal::Str {
+                terminated: false,
+                ..
+            })
+        );
+        match token.kind {
+            TokenKind::Unknown => Some(LexError::new(
+                LexErrorKind::UnexpectedCharacter,
+                format!("Unexpected character `{}`", token.lexeme),
+                token.span,
+            )),
+            TokenKind::String if unterminated => Some(LexError::new(
+                LexErrorKind::UnterminatedString,
+                "Unterminated string".to_string(),
+                token.span,
+            )),
+            TokenKind::BlockComment if unterminated => Some(LexError::new(
+                LexErrorKind::UnterminatedBlockComment,
+                "Unterminated block comment".to_string(),
+                token.span,
+            )),
+            _ => None,
+        }
     }
-}
 
-impl Error for LexError {
-    fn line(&self) -> u32 {
-        self.line
+    pub fn line(&self) -> u32 {
+        self.span.line
     }
 
-    fn message(&self) -> String {
-        self.message.clone()
+    pub fn code(&self) -> &'static str {
+        match self.kind {
+            LexErrorKind::UnterminatedString => "E0101",
+            LexErrorKind::UnexpectedCharacter => "E0102",
+            LexErrorKind::UnterminatedBlockComment => "E0103",
+        }
     }
 }
+
+impl std::fmt::Display for LexError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for LexError {}
@@ -0,0 +1,223 @@
+use super::{
+    CLArgsError, Diagnostic, FormatError, FormatErrorKind, IOError, JsonError, LexError,
+    ResolveError, RpnError, RuntimeError, TypeError,
+};
+use crate::lexer::token::Span;
+use crate::parser::ParserError;
+
+// every way running a lox program can fail. each variant wraps the error
+// from the stage that raised it. the wrapper's Display already includes
+// that error's message, so it isn't handed back from `source()` too, or
+// anything that walks the chain would print it twice
+#[derive(Debug)]
+pub enum LoxError {
+    Lex(LexError),
+    Parse(ParserError),
+    Resolve(ResolveError),
+    Type(TypeError),
+    Runtime(RuntimeError),
+    Io(IOError),
+    Usage(CLArgsError),
+    Json(JsonError),
+    Rpn(RpnError),
+    Format(FormatError),
+}
+
+impl LoxError {
+    // stable identifier for the kind of error, e.g. `E0101`. codes are
+    // grouped by stage: E00xx driver and input, E01xx lexer, E02xx parser,
+    // E03xx resolver and E04xx runtime. never reuse or renumber a code
+    pub fn code(&self) -> &'static str {
+        match self {
+            LoxError::Lex(e) => e.code(),
+            LoxError::Parse(e) => e.code(),
+            LoxError::Resolve(e) => e.code(),
+            LoxError::Type(e) => e.code(),
+            LoxError::Runtime(e) => e.code(),
+            LoxError::Io(e) => e.code(),
+            LoxError::Usage(e) => e.code(),
+            LoxError::Json(e) => e.code(),
+            LoxError::Rpn(e) => e.code(),
+            LoxError::Format(e) => e.code(),
+        }
+    }
+
+    // the source the error points at, io and usage errors don't have one
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            LoxError::Lex(e) => Some(e.span),
+            LoxError::Parse(e) => Some(e.span()),
+            LoxError::Resolve(e) => Some(e.span),
+            LoxError::Type(e) => Some(e.span),
+            LoxError::Runtime(e) => Some(e.span),
+            LoxError::Rpn(e) => Some(e.span),
+            LoxError::Io(_) | LoxError::Usage(_) | LoxError::Json(_) | LoxError::Format(_) => None,
+        }
+    }
+
+    pub fn line(&self) -> u32 {
+        match self {
+            LoxError::Io(e) => e.line,
+            LoxError::Usage(e) => e.line,
+            LoxError::Json(e) => e.line,
+            LoxError::Format(e) => e.line,
+            _ => self.span().map_or(0, |span| span.line),
+        }
+    }
+
+    // process exit status, following the sysexits.h codes jlox uses.
+    // unformatted source exits with 1, like other formatters' check modes
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            LoxError::Format(e) if e.kind == FormatErrorKind::Unformatted => 1,
+            LoxError::Format(_) => 70,
+            LoxError::Usage(_) => 64,
+            LoxError::Lex(_)
+            | LoxError::Parse(_)
+            | LoxError::Resolve(_)
+            | LoxError::Type(_)
+            | LoxError::Json(_)
+            | LoxError::Rpn(_) => 65,
+            LoxError::Runtime(_) => 70,
+            LoxError::Io(_) => 74,
+        }
+    }
+
+    // the message from the stage that raised the error, without
+    // any of the line or code decoration
+    pub fn message(&self) -> String {
+        self.inner().to_string()
+    }
+
+    fn inner(&self) -> &(dyn std::error::Error + 'static) {
+        match self {
+            LoxError::Lex(e) => e,
+            LoxError::Parse(e) => e,
+            LoxError::Resolve(e) => e,
+            LoxError::Type(e) => e,
+            LoxError::Runtime(e) => e,
+            LoxError::Io(e) => e,
+            LoxError::Usage(e) => e,
+            LoxError::Json(e) => e,
+            LoxError::Rpn(e) => e,
+            LoxError::Format(e) => e,
+        }
+    }
+}
+
+impl std::fmt::Display for LoxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", Diagnostic::from(self))
+    }
+}
+
+impl std::error::Error for LoxError {}
+
+impl From<LexError> for LoxError {
+    fn from(e: LexError) -> LoxError {
+        LoxError::Lex(e)
+    }
+}
+
+impl From<ParserError> for LoxError {
+    fn from(e: ParserError) -> LoxError {
+        LoxError::Parse(e)
+    }
+}
+
+impl From<ResolveError> for LoxError {
+    fn from(e: ResolveError) -> LoxError {
+        LoxError::Resolve(e)
+    }
+}
+
+impl From<TypeError> for LoxError {
+    fn from(e: TypeError) -> LoxError {
+        LoxError::Type(e)
+    }
+}
+
+impl From<RuntimeError> for LoxError {
+    fn from(e: RuntimeError) -> LoxError {
+        LoxError::Runtime(e)
+    }
+}
+
+impl From<IOError> for LoxError {
+    fn from(e: IOError) -> LoxError {
+        LoxError::Io(e)
+    }
+}
+
+impl From<JsonError> for LoxError {
+    fn from(e: JsonError) -> LoxError {
+        LoxError::Json(e)
+    }
+}
+
+impl From<RpnError> for LoxError {
+    fn from(e: RpnError) -> LoxError {
+        LoxError::Rpn(e)
+    }
+}
+
+impl From<FormatError> for LoxError {
+    fn from(e: FormatError) -> LoxError {
+        LoxError::Format(e)
+    }
+}
+
+impl From<CLArgsError> for LoxError {
+    fn from(e: CLArgsError) -> LoxError {
+        LoxError::Usage(e)
+    }
+}
+
+// lets `?` lift a single error into the list of errors a run reports
+impl From<LoxError> for Vec<LoxError> {
+    fn from(e: LoxError) -> Vec<LoxError> {
+        vec![e]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LoxError;
+    use crate::error_handling::{IOError, RuntimeError, RuntimeErrorKind};
+    use crate::lexer::token::Span;
+    use std::error::Error;
+    use std::io;
+
+    // the error and everything under it, the way error reporting
+    // libraries print a chain
+    fn chain(err: &dyn Error) -> String {
+        let mut out = err.to_string();
+        let mut source = err.source();
+        while let Some(err) = source {
+            out.push_str(&format!("\ncaused by: {}", err));
+            source = err.source();
+        }
+        out
+    }
+
+    #[test]
+    fn chains_print_each_message_once() {
+        let errors = [
+            (
+                LoxError::Io(IOError::new(0, io::Error::other("disk on fire"))),
+                "disk on fire",
+            ),
+            (
+                LoxError::Runtime(RuntimeError::new(
+                    RuntimeErrorKind::OperandMustBeNumber,
+                    Span::default(),
+                )),
+                "Operand must be a number",
+            ),
+        ];
+        for (err, message) in errors {
+            assert_eq!(chain(&err).matches(message).count(), 1, "{}", chain(&err));
+            assert_eq!(err.message(), message);
+        }
+    }
+}
@@ -1,34 +1,28 @@
+pub mod codes;
+mod diagnostic;
+mod format_error;
+mod json_error;
 mod lex_error;
-pub use lex_error::LexError;
+mod lox_error;
+mod rpn_error;
+mod runtime_error;
+mod stack_trace;
+pub use diagnostic::{Diagnostic, Severity};
+pub use format_error::{FormatError, FormatErrorKind};
+pub use json_error::JsonError;
+pub use lex_error::{LexError, LexErrorKind};
+pub use lox_error::LoxError;
+pub use rpn_error::RpnError;
+pub use runtime_error::{ResolveError, RuntimeError, RuntimeErrorKind, TypeError};
+pub use stack_trace::{Frame, StackTrace};
 
-// create custom error trait for all error types to implement
-pub trait Error {
-    fn line(&self) -> u32;
-    fn message(&self) -> String;
-}
-
-impl std::fmt::Display for Box<dyn Error> {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "[line {}] Error: {}", self.line(), self.message())
-    }
-}
-
-impl std::fmt::Debug for Box<dyn Error> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("Error")
-            .field("line: {}", &self.line())
-            .field("message: {}", &self.message())
-            .finish()
-    }
-}
-
-fn report_error_str(line: u32, message: String) -> String {
-    format!("[Line {line}] Error: {message}\n")
+fn report_error_str(line: u32, label: &str, code: &str, message: &str) -> String {
+    format!("[Line {line}] {label}[{code}]: {message}")
 }
 
 #[allow(dead_code)]
-fn report_error(line: u32, message: String) {
-    print!("{}", report_error_str(line, message));
+fn report_error(line: u32, code: &str, message: &str) {
+    eprintln!("{}", report_error_str(line, "Error", code, message));
 }
 
 #[derive(Debug)]
@@ -41,51 +35,41 @@ impl CLArgsError {
     pub fn new(line: u32, message: String) -> CLArgsError {
         CLArgsError { line, message }
     }
+
+    pub fn code(&self) -> &'static str {
+        "E0002"
+    }
 }
 
 impl std::fmt::Display for CLArgsError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", report_error_str(self.line, self.message.clone()))
+        write!(f, "{}", self.message)
     }
 }
 
 impl std::error::Error for CLArgsError {}
 
-impl Error for CLArgsError {
-    fn line(&self) -> u32 {
-        self.line
-    }
-    fn message(&self) -> String {
-        self.message.clone()
-    }
-}
-
 #[derive(Debug)]
 pub struct IOError {
     pub line: u32,
-    pub message: String,
+    pub source: std::io::Error,
 }
 
 impl std::fmt::Display for IOError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", report_error_str(self.line, self.message.clone()))
+        write!(f, "{}", self.source)
     }
 }
 
 impl IOError {
     pub fn new(line: u32, err: std::io::Error) -> IOError {
-        IOError {
-            line,
-            message: err.to_string(),
-        }
+        IOError { line, source: err }
     }
-}
 
-impl Error for IOError {
-    fn line(&self) -> u32 {
-        self.line
-    }
-    fn message(&self) -> String {
-        self.message.clone()
+    pub fn code(&self) -> &'static str {
+        "E0001"
     }
 }
+
+// Display is the io error's own message, so it isn't the `source()` too
+impl std::error::Error for IOError {}
@@ -0,0 +1,27 @@
+use crate::lexer::token::Span;
+
+// reverse polish text that doesn't describe an expression, like an
+// operator without enough operands or a word we don't know
+#[derive(Debug)]
+pub struct RpnError {
+    pub message: String,
+    pub span: Span,
+}
+
+impl RpnError {
+    pub fn new(message: String, span: Span) -> RpnError {
+        RpnError { message, span }
+    }
+
+    pub fn code(&self) -> &'static str {
+        "E0004"
+    }
+}
+
+impl std::fmt::Display for RpnError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for RpnError {}
@@ -0,0 +1,116 @@
+use super::StackTrace;
+use crate::lexer::token::Span;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuntimeErrorKind {
+    OperandMustBeNumber,
+    OperandsMustBeNumbers,
+    OperandsMustBeNumbersOrStrings,
+}
+
+impl RuntimeErrorKind {
+    pub fn code(&self) -> &'static str {
+        match self {
+            RuntimeErrorKind::OperandMustBeNumber => "E0401",
+            RuntimeErrorKind::OperandsMustBeNumbers => "E0402",
+            RuntimeErrorKind::OperandsMustBeNumbersOrStrings => "E0403",
+        }
+    }
+}
+
+impl std::fmt::Display for RuntimeErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RuntimeErrorKind::OperandMustBeNumber => write!(f, "Operand must be a number"),
+            RuntimeErrorKind::OperandsMustBeNumbers => write!(f, "Operands must be numbers"),
+            RuntimeErrorKind::OperandsMustBeNumbersOrStrings => {
+                write!(f, "Operands must be two numbers or two strings")
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct RuntimeError {
+    pub kind: RuntimeErrorKind,
+    pub span: Span,
+    // calls the error unwound through, empty at the top level
+    pub trace: StackTrace,
+}
+
+impl RuntimeError {
+    pub fn new(kind: RuntimeErrorKind, span: Span) -> RuntimeError {
+        RuntimeError {
+            kind,
+            span,
+            trace: StackTrace::new(),
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        self.kind.code()
+    }
+}
+
+impl std::fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.kind)
+    }
+}
+
+impl std::error::Error for RuntimeError {}
+
+// errors found while resolving names ahead of running a program. there
+// is no resolver yet, this is here so LoxError already has a home for them
+#[derive(Debug)]
+pub struct ResolveError {
+    pub message: String,
+    pub span: Span,
+}
+
+impl ResolveError {
+    pub fn new(message: String, span: Span) -> ResolveError {
+        ResolveError { message, span }
+    }
+
+    pub fn code(&self) -> &'static str {
+        "E0301"
+    }
+}
+
+impl std::fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for ResolveError {}
+
+// a runtime error we know is coming without running the program, because
+// the types of the operands are known from the source. it has the same
+// code as the runtime error it predicts
+#[derive(Debug)]
+pub struct TypeError {
+    pub kind: RuntimeErrorKind,
+    // what was found instead, e.g. "string and number"
+    pub found: String,
+    pub span: Span,
+}
+
+impl TypeError {
+    pub fn new(kind: RuntimeErrorKind, found: String, span: Span) -> TypeError {
+        TypeError { kind, found, span }
+    }
+
+    pub fn code(&self) -> &'static str {
+        self.kind.code()
+    }
+}
+
+impl std::fmt::Display for TypeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}, found {}", self.kind, self.found)
+    }
+}
+
+impl std::error::Error for TypeError {}
@@ -0,0 +1,154 @@
+// the call stack at the point a runtime error was raised. there are no
+// functions yet, so nothing pushes frames until there are (see
+// BACKLOG.md), but both the tree walking interpreter and any vm should
+// build traces through this type so they print the same way. frames are
+// pushed as the error unwinds, which puts them innermost first
+
+// traces longer than this are cut down to the innermost frames and a
+// count of the rest, deep recursion would otherwise bury the error
+const MAX_FRAMES: usize = 16;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub function: String,
+    // None for code that didn't come from a file, e.g. the prompt
+    pub file: Option<String>,
+    pub line: u32,
+}
+
+impl Frame {
+    pub fn new(function: String, file: Option<String>, line: u32) -> Frame {
+        Frame {
+            function,
+            file,
+            line,
+        }
+    }
+}
+
+impl std::fmt::Display for Frame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.file {
+            Some(file) => write!(f, "at {} ({}:{})", self.function, file, self.line),
+            None => write!(f, "at {} (line {})", self.function, self.line),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StackTrace {
+    frames: Vec<Frame>,
+}
+
+impl StackTrace {
+    pub fn new() -> StackTrace {
+        StackTrace::default()
+    }
+
+    // add the frame the error is unwinding out of, callers go after callees
+    pub fn push(&mut self, frame: Frame) {
+        self.frames.push(frame);
+    }
+
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    // the trace as it's shown to the user, one entry per line, with
+    // anything past MAX_FRAMES collapsed into a single line
+    fn lines(&self) -> Vec<String> {
+        let mut lines: Vec<String> = self
+            .frames
+            .iter()
+            .take(MAX_FRAMES)
+            .map(|frame| frame.to_string())
+            .collect();
+        if self.frames.len() > MAX_FRAMES {
+            lines.push(format!(
+                "... {} more frames",
+                self.frames.len() - MAX_FRAMES
+            ));
+        }
+        lines
+    }
+}
+
+// the one way a trace is printed, indented under the error it belongs to
+// with no newline after the last frame
+impl std::fmt::Display for StackTrace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let lines: Vec<String> = self
+            .lines()
+            .iter()
+            .map(|line| format!("    {}", line))
+            .collect();
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Frame, StackTrace, MAX_FRAMES};
+    use crate::error_handling::{Diagnostic, LoxError, RuntimeError, RuntimeErrorKind};
+    use crate::lexer::token::Span;
+
+    // a trace unwound out of `depth` nested calls to `f<n>`, `f<depth>`
+    // being the innermost
+    fn trace(depth: u32) -> StackTrace {
+        let mut trace = StackTrace::new();
+        for n in (1..=depth).rev() {
+            trace.push(Frame::new(
+                format!("f{}", n),
+                Some("main.lox".to_string()),
+                n,
+            ));
+        }
+        trace
+    }
+
+    #[test]
+    fn frames() {
+        let frame = Frame::new("f".to_string(), Some("main.lox".to_string()), 3);
+        assert_eq!(frame.to_string(), "at f (main.lox:3)");
+        let frame = Frame::new("f".to_string(), None, 3);
+        assert_eq!(frame.to_string(), "at f (line 3)");
+    }
+
+    #[test]
+    fn innermost_frame_first() {
+        assert_eq!(
+            trace(3).to_string(),
+            "    at f3 (main.lox:3)\n    at f2 (main.lox:2)\n    at f1 (main.lox:1)"
+        );
+        assert_eq!(StackTrace::new().to_string(), "");
+    }
+
+    #[test]
+    fn deep_traces_collapse() {
+        let full = trace(MAX_FRAMES as u32).to_string();
+        assert_eq!(full.lines().count(), MAX_FRAMES);
+        assert!(!full.contains("more frames"));
+
+        let deep = trace(MAX_FRAMES as u32 + 5).to_string();
+        let lines: Vec<&str> = deep.lines().collect();
+        assert_eq!(lines.len(), MAX_FRAMES + 1);
+        assert_eq!(lines[0], "    at f21 (main.lox:21)");
+        assert_eq!(lines[MAX_FRAMES - 1], "    at f6 (main.lox:6)");
+        assert_eq!(lines[MAX_FRAMES], "    ... 5 more frames");
+        // only the printed trace is cut down
+        assert_eq!(trace(MAX_FRAMES as u32 + 5).frames().len(), MAX_FRAMES + 5);
+    }
+
+    #[test]
+    fn diagnostics_print_the_trace_under_the_error() {
+        let mut error = RuntimeError::new(RuntimeErrorKind::OperandMustBeNumber, Span::default());
+        error.trace = trace(MAX_FRAMES as u32 + 1);
+        let expected = format!("\n{}", error.trace);
+        let diagnostic = Diagnostic::from(&LoxError::Runtime(error));
+        assert!(diagnostic.to_string().ends_with(&expected));
+    }
+}
@@ -0,0 +1,92 @@
+use crate::lexer::token::TokenKind;
+use crate::parser::infix_binding;
+use crate::syntax::expr::*;
+use crate::syntax::printer::SourcePrinter;
+
+// how a tree is laid out, before deciding where lines break. a group is
+// printed on one line if it fits, otherwise every `Line` directly in it
+// (not in a nested group) becomes a newline. every `Text` is exactly one
+// token of the source, in source order, which is how comments find their
+// place again when the layout is printed
+#[derive(Debug, Clone, PartialEq)]
+pub enum Doc {
+    Text(String),
+    // always a single space
+    Space,
+    // a space, or a newline if the group it's in doesn't fit
+    Line,
+    Group(Vec<Doc>),
+    // lines broken inside are indented one more level
+    Indent(Vec<Doc>),
+}
+
+impl Doc {
+    fn text(text: &str) -> Doc {
+        Doc::Text(text.to_string())
+    }
+}
+
+// operators break after themselves, `a +` then `b` on the next line,
+// while conditionals break before the `?` and the `:`. chains of the same
+// operator are one group so they break together instead of stepping in
+// a level for every operand
+pub fn layout(e: &Expr) -> Doc {
+    match e {
+        Expr::Binary(b) => {
+            let (precedence, _) = infix_binding(b.operator.kind);
+            let mut operands = vec![&*b.right];
+            let mut operators = vec![&b.operator];
+            let mut left = &*b.left;
+            // only left associative operators get here, so same precedence
+            // operators nest on the left
+            while let Expr::Binary(inner) = left {
+                if infix_binding(inner.operator.kind).0 != precedence {
+                    break;
+                }
+                operands.push(&inner.right);
+                operators.push(&inner.operator);
+                left = &inner.left;
+            }
+            let mut rest = Vec::new();
+            for (operator, operand) in operators.iter().zip(&operands).rev() {
+                if operator.kind != TokenKind::Comma {
+                    rest.push(Doc::Space);
+                }
+                rest.push(Doc::text(&operator.lexeme));
+                rest.push(Doc::Line);
+                rest.push(layout(operand));
+            }
+            Doc::Group(vec![layout(left), Doc::Indent(rest)])
+        }
+        Expr::Conditional(c) => {
+            let first = layout(&c.condition);
+            let mut rest = Vec::new();
+            let mut c = c;
+            loop {
+                rest.extend([
+                    Doc::Line,
+                    Doc::text("?"),
+                    Doc::Space,
+                    layout(&c.then_branch),
+                ]);
+                rest.extend([Doc::Line, Doc::text(":"), Doc::Space]);
+                match &*c.else_branch {
+                    Expr::Conditional(inner) => {
+                        rest.push(layout(&inner.condition));
+                        c = inner;
+                    }
+                    other => {
+                        rest.push(layout(other));
+                        break;
+                    }
+                }
+            }
+            Doc::Group(vec![first, Doc::Indent(rest)])
+        }
+        Expr::Grouping(g) => {
+            Doc::Group(vec![Doc::text("("), layout(&g.expression), Doc::text(")")])
+        }
+        Expr::Literal(l) => Doc::Text(SourcePrinter.visit_literal_expr(l)),
+        Expr::Unary(u) => Doc::Group(vec![Doc::text(&u.operator.lexeme), layout(&u.right)]),
+    }
+}
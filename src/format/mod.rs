@@ -0,0 +1,339 @@
+// an opinionated formatter for lox source, behind `lox_interpreter fmt`.
+// the source is parsed and printed back out from the tree, so spacing is
+// always the same: binary operators and `?:` get a space either side,
+// unary operators and parens hug their operand and commas are followed by
+// a space. expressions wider than the line are broken at their operators
+// with continuation lines indented four spaces. lox only has expressions
+// so far, there are no blocks to indent or braces to place yet.
+//
+// comments aren't in the tree, so they're put back next to the token
+// they were written by: a comment that shares a line with the token
+// before it stays at the end of that token's line, any other comment
+// goes on its own line before the next token. formatting is idempotent,
+// and the formatted source is parsed again to check it gives the same
+// tree before anything is written
+mod layout;
+
+pub use layout::{layout, Doc};
+
+use crate::error_handling::{FormatError, FormatErrorKind, LoxError};
+use crate::lexer::token::{Token, TokenKind};
+use crate::lexer::{is_comment, scan_tokens, scan_tokens_with_comments};
+use crate::parser::parse;
+use crate::syntax::Structural;
+
+pub const DEFAULT_WIDTH: usize = 80;
+const INDENT: usize = 4;
+
+struct Comment {
+    text: String,
+    // a line comment, or a block comment with a newline in it, so
+    // nothing else can follow it on the line
+    ends_line: bool,
+    // there's no token before it on the line it starts on
+    own_line: bool,
+}
+
+// the comments that go around one token
+#[derive(Default)]
+struct Attached {
+    before: Vec<Comment>,
+    after: Vec<Comment>,
+}
+
+pub fn format_source(source: &str, width: usize) -> Result<String, Vec<LoxError>> {
+    let (comments, tokens): (Vec<Token>, Vec<Token>) =
+        scan_tokens_with_comments(source).partition(is_comment);
+    let expr = parse(tokens.iter().cloned())?;
+    let doc = layout(&expr);
+    let tokens: Vec<Token> = tokens
+        .into_iter()
+        .filter(|token| token.kind != TokenKind::EOF)
+        .collect();
+    // comments are attached to tokens by index, so the layout has to print
+    // every token of the source and nothing else
+    let changed_tree = || LoxError::from(FormatError::new(FormatErrorKind::ChangedTree, 0));
+    if texts(&doc) != tokens.len() {
+        return Err(changed_tree().into());
+    }
+
+    let mut printer = Printer {
+        width,
+        attached: attach(source, &tokens, &comments),
+        out: String::new(),
+        indent: 0,
+        next: 0,
+        pending_newline: false,
+    };
+    printer.print(&doc, false);
+    let formatted = printer.finish();
+
+    // comments can't change the tree, so this only needs the tokens
+    match parse(scan_tokens(&formatted)) {
+        Ok(formatted_expr) if formatted_expr.structural_eq(&expr) => Ok(formatted),
+        _ => Err(changed_tree().into()),
+    }
+}
+
+// how many tokens the layout prints
+fn texts(doc: &Doc) -> usize {
+    match doc {
+        Doc::Text(_) => 1,
+        Doc::Space | Doc::Line => 0,
+        Doc::Group(docs) | Doc::Indent(docs) => docs.iter().map(texts).sum(),
+    }
+}
+
+// give every comment to the token it belongs with
+fn attach(source: &str, tokens: &[Token], comments: &[Token]) -> Vec<Attached> {
+    let mut attached: Vec<Attached> = tokens.iter().map(|_| Attached::default()).collect();
+    for comment in comments {
+        let text = source[comment.span.start..comment.span.end].trim_end();
+        let previous = tokens
+            .iter()
+            .rposition(|token| token.span.start < comment.span.start);
+        let trailing = previous.is_some_and(|i| end_line(source, &tokens[i]) == comment.line);
+        let comment = Comment {
+            text: text.to_string(),
+            ends_line: comment.kind == TokenKind::Comment || text.contains('\n'),
+            own_line: !trailing,
+        };
+        match previous {
+            Some(i) if trailing => attached[i].after.push(comment),
+            // on its own line after the last token
+            Some(i) if i + 1 == tokens.len() => attached[i].after.push(comment),
+            Some(i) => attached[i + 1].before.push(comment),
+            None => attached[0].before.push(comment),
+        }
+    }
+    attached
+}
+
+// the line a token finishes on, strings can run over several
+fn end_line(source: &str, token: &Token) -> u32 {
+    let text = &source[token.span.start..token.span.end];
+    token.line + text.matches('\n').count() as u32
+}
+
+struct Printer {
+    width: usize,
+    attached: Vec<Attached>,
+    out: String,
+    indent: usize,
+    // index of the token the next `Text` prints
+    next: usize,
+    // a comment ended the line, so the next thing printed starts a new one
+    pending_newline: bool,
+}
+
+impl Printer {
+    fn print(&mut self, doc: &Doc, flat: bool) {
+        match doc {
+            Doc::Text(text) => self.text(text),
+            Doc::Line if !flat => self.newline(self.indent),
+            Doc::Space | Doc::Line => {
+                if !self.pending_newline {
+                    self.out.push(' ');
+                }
+            }
+            Doc::Group(docs) => {
+                let flat = flat || self.fits(docs);
+                for doc in docs {
+                    self.print(doc, flat);
+                }
+            }
+            Doc::Indent(docs) => {
+                self.indent += INDENT;
+                for doc in docs {
+                    self.print(doc, flat);
+                }
+                self.indent -= INDENT;
+            }
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        let attached = std::mem::take(&mut self.attached[self.next]);
+        self.next += 1;
+        for comment in &attached.before {
+            if !self.at_line_start() {
+                self.newline(self.indent);
+            }
+            self.out.push_str(&comment.text);
+            self.newline(self.indent);
+        }
+        if self.pending_newline {
+            self.newline(self.indent);
+        }
+        self.out.push_str(text);
+        for comment in &attached.after {
+            if comment.own_line {
+                // only comments after the last token get here
+                self.newline(0);
+            } else {
+                self.out.push(' ');
+            }
+            self.out.push_str(&comment.text);
+            self.pending_newline |= comment.ends_line || comment.own_line;
+        }
+    }
+
+    fn newline(&mut self, indent: usize) {
+        self.out.truncate(self.out.trim_end_matches(' ').len());
+        self.out.push('\n');
+        self.out.push_str(&" ".repeat(indent));
+        self.pending_newline = false;
+    }
+
+    fn column(&self) -> usize {
+        let line = self.out.rsplit('\n').next().unwrap_or_default();
+        line.chars().count()
+    }
+
+    fn at_line_start(&self) -> bool {
+        let line = self.out.rsplit('\n').next().unwrap_or_default();
+        line.trim().is_empty()
+    }
+
+    // whether the docs fit on the rest of the line. comments that end the
+    // line only fit at the very end, and comments on their own line only
+    // at the very start, where they're printed before the group begins.
+    // line comments can run past the width, block comments count
+    fn fits(&self, docs: &[Doc]) -> bool {
+        let mut measure = Measure {
+            left: self.width as isize - self.column() as isize,
+            next: self.next,
+            started: false,
+            line_ended: false,
+        };
+        docs.iter().all(|doc| self.measure(doc, &mut measure)) && measure.left >= 0
+    }
+
+    fn measure(&self, doc: &Doc, m: &mut Measure) -> bool {
+        match doc {
+            Doc::Text(text) => {
+                let attached = &self.attached[m.next];
+                m.next += 1;
+                if m.line_ended || (m.started && !attached.before.is_empty()) {
+                    return false;
+                }
+                m.started = true;
+                m.left -= text.chars().count() as isize;
+                for comment in &attached.after {
+                    if comment.ends_line || comment.own_line {
+                        m.line_ended = true;
+                    } else {
+                        m.left -= comment.text.chars().count() as isize + 1;
+                    }
+                }
+                m.left >= 0
+            }
+            Doc::Space | Doc::Line => {
+                m.left -= 1;
+                !m.line_ended && m.left >= 0
+            }
+            Doc::Group(docs) | Doc::Indent(docs) => docs.iter().all(|doc| self.measure(doc, m)),
+        }
+    }
+
+    fn finish(mut self) -> String {
+        self.out.truncate(self.out.trim_end().len());
+        self.out.push('\n');
+        self.out
+    }
+}
+
+struct Measure {
+    left: isize,
+    next: usize,
+    started: bool,
+    line_ended: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_source, DEFAULT_WIDTH};
+    use crate::lexer::{is_comment, scan_tokens, scan_tokens_with_comments};
+    use crate::parser::parse;
+    use crate::syntax::Structural;
+
+    const SOURCES: &[&str] = &[
+        "1+2*3",
+        "  (1 + 2)  *  -3 ",
+        "1 ? 2 : 3 ? 4 : 5, !true",
+        "\"a\" + \"b\" + \"c\" ? 1 * 2 * 3 : 4 - 5 - 6 - 7 - 8 - 9 - 10 - 11 - 12 - 13 - 14 - 15",
+        "// top\n1 + 2 // trailing\n  * /* inline */ 3\n",
+        "/* a */ 1 /* b */ + /* c\n d */ 2 // e\n// f\n",
+        "1 +\r\n// own line\r\n2\r\n",
+    ];
+
+    fn format(source: &str) -> String {
+        format_source(source, DEFAULT_WIDTH).unwrap()
+    }
+
+    // a line comment's lexeme keeps the `\r` of a `\r\n`, which
+    // formatting drops along with the rest of the line ending
+    fn comments(source: &str) -> Vec<String> {
+        scan_tokens_with_comments(source)
+            .filter(is_comment)
+            .map(|comment| comment.lexeme.trim_end().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn formatting_is_idempotent() {
+        for source in SOURCES {
+            let formatted = format(source);
+            assert_eq!(format(&formatted), formatted, "{source:?}");
+            for width in [8, 20, 40] {
+                let formatted = format_source(source, width).unwrap();
+                assert_eq!(format_source(&formatted, width).unwrap(), formatted);
+            }
+        }
+    }
+
+    #[test]
+    fn formatting_never_changes_the_tree() {
+        for source in SOURCES {
+            let before = parse(scan_tokens(source)).unwrap();
+            for width in [8, DEFAULT_WIDTH] {
+                let after = parse(scan_tokens(&format_source(source, width).unwrap())).unwrap();
+                assert!(after.structural_eq(&before), "{source:?} at {width}");
+            }
+        }
+    }
+
+    #[test]
+    fn comments_are_kept_in_order() {
+        for source in SOURCES {
+            assert_eq!(comments(&format(source)), comments(source), "{source:?}");
+        }
+    }
+
+    #[test]
+    fn comments_stay_next_to_their_tokens() {
+        assert_eq!(format("1 + 2 // two\n"), "1 + 2 // two\n");
+        assert_eq!(format("// first\n1 + 2\n"), "// first\n1 + 2\n");
+        assert_eq!(format("1 +\n// two\n2\n"), "1 +\n    // two\n    2\n");
+        assert_eq!(format("1 /* one */ + 2\n"), "1 /* one */ + 2\n");
+        assert_eq!(format("1 + 2\n// end\n"), "1 + 2\n// end\n");
+    }
+
+    #[test]
+    fn wraps_at_the_width() {
+        let source = "1 + 2 + 3 + 4 + 5 + 6";
+        assert_eq!(format(source), "1 + 2 + 3 + 4 + 5 + 6\n");
+        let wrapped = format_source(source, 12).unwrap();
+        assert_eq!(wrapped, "1 +\n    2 +\n    3 +\n    4 +\n    5 +\n    6\n");
+        assert!(wrapped.lines().all(|line| line.len() <= 12));
+        assert_eq!(
+            format_source("\"a\" + \"b\" + \"c\" ? 1 * 2 * 3 : 4 - 5 - 6", 16).unwrap(),
+            "\"a\" + \"b\" + \"c\"\n    ? 1 * 2 * 3\n    : 4 - 5 - 6\n"
+        );
+    }
+
+    #[test]
+    fn trailing_tokens_are_an_error() {
+        assert!(format_source("1 + 2 3", DEFAULT_WIDTH).is_err());
+    }
+}
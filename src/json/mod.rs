@@ -0,0 +1,32 @@
+// minimal helpers for writing and reading json by hand, we keep the
+// dependency list empty so there is no serde to lean on
+mod value;
+pub use value::{parse, Value, MAX_DEPTH};
+
+// quote and escape a string so it can be embedded in a json document
+pub fn quote(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+// json has no representation for nan or the infinities, so those become null
+pub fn number(n: f64) -> String {
+    if n.is_finite() {
+        n.to_string()
+    } else {
+        "null".to_string()
+    }
+}
@@ -0,0 +1,274 @@
+use crate::error_handling::JsonError;
+
+// a parsed json document. objects keep their keys in document order
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    // the value of `key`, if this is an object that has it
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    // name of the json type, for error messages
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Null => "null",
+            Value::Bool(_) => "boolean",
+            Value::Number(_) => "number",
+            Value::String(_) => "string",
+            Value::Array(_) => "array",
+            Value::Object(_) => "object",
+        }
+    }
+}
+
+// parse a whole json document, anything but whitespace after the
+// value is an error
+pub fn parse(source: &str) -> Result<Value, JsonError> {
+    let mut reader = Reader {
+        chars: source.chars().peekable(),
+        line: 1,
+        depth: 0,
+    };
+    let value = reader.value()?;
+    reader.skip_whitespace();
+    match reader.chars.peek().copied() {
+        None => Ok(value),
+        Some(c) => Err(reader.error(format!("Unexpected `{c}` after the end of the document"))),
+    }
+}
+
+// how deeply arrays and objects can nest. the reader recurses for each
+// level, so without a limit a document of a few thousand `[`s from some
+// other tool would overflow the stack
+pub const MAX_DEPTH: usize = 512;
+
+struct Reader<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    line: u32,
+    depth: usize,
+}
+
+impl Reader<'_> {
+    fn error(&self, message: String) -> JsonError {
+        JsonError::new(self.line, message)
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next();
+        if c == Some('\n') {
+            self.line += 1;
+        }
+        c
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.chars.peek(), Some(' ' | '\t' | '\n' | '\r')) {
+            self.next();
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), JsonError> {
+        self.skip_whitespace();
+        match self.next() {
+            Some(c) if c == expected => Ok(()),
+            Some(c) => Err(self.error(format!("Expected `{expected}` but found `{c}`"))),
+            None => Err(self.error(format!("Expected `{expected}` but the document ended"))),
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, JsonError> {
+        self.skip_whitespace();
+        match self.chars.peek().copied() {
+            Some('{') => self.nested(Self::object),
+            Some('[') => self.nested(Self::array),
+            Some('"') => Ok(Value::String(self.string()?)),
+            Some('-' | '0'..='9') => self.number(),
+            Some('t') => self.keyword("true", Value::Bool(true)),
+            Some('f') => self.keyword("false", Value::Bool(false)),
+            Some('n') => self.keyword("null", Value::Null),
+            Some(c) => Err(self.error(format!("Expected a value but found `{c}`"))),
+            None => Err(self.error("Expected a value but the document ended".to_string())),
+        }
+    }
+
+    fn keyword(&mut self, keyword: &str, value: Value) -> Result<Value, JsonError> {
+        for expected in keyword.chars() {
+            if self.next() != Some(expected) {
+                return Err(self.error(format!("Expected `{keyword}`")));
+            }
+        }
+        Ok(value)
+    }
+
+    fn nested(
+        &mut self,
+        read: fn(&mut Self) -> Result<Value, JsonError>,
+    ) -> Result<Value, JsonError> {
+        if self.depth == MAX_DEPTH {
+            return Err(self.error(format!("Nested more than {MAX_DEPTH} levels deep")));
+        }
+        self.depth += 1;
+        let value = read(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn object(&mut self) -> Result<Value, JsonError> {
+        self.expect('{')?;
+        let mut fields = Vec::new();
+        self.skip_whitespace();
+        if self.chars.peek() == Some(&'}') {
+            self.next();
+            return Ok(Value::Object(fields));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.chars.peek() != Some(&'"') {
+                return Err(self.error("Expected a string for the object key".to_string()));
+            }
+            let key = self.string()?;
+            self.expect(':')?;
+            fields.push((key, self.value()?));
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => continue,
+                Some('}') => return Ok(Value::Object(fields)),
+                _ => return Err(self.error("Expected `,` or `}` in object".to_string())),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Value, JsonError> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.chars.peek() == Some(&']') {
+            self.next();
+            return Ok(Value::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => continue,
+                Some(']') => return Ok(Value::Array(items)),
+                _ => return Err(self.error("Expected `,` or `]` in array".to_string())),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, JsonError> {
+        self.expect('"')?;
+        let mut out = String::new();
+        loop {
+            match self.next() {
+                Some('"') => return Ok(out),
+                Some('\\') => match self.next() {
+                    Some('"') => out.push('"'),
+                    Some('\\') => out.push('\\'),
+                    Some('/') => out.push('/'),
+                    Some('b') => out.push('\u{8}'),
+                    Some('f') => out.push('\u{c}'),
+                    Some('n') => out.push('\n'),
+                    Some('r') => out.push('\r'),
+                    Some('t') => out.push('\t'),
+                    Some('u') => out.push(self.unicode_escape()?),
+                    _ => return Err(self.error("Invalid escape in string".to_string())),
+                },
+                Some(c) if (c as u32) < 0x20 => {
+                    return Err(self.error("Unescaped control character in string".to_string()))
+                }
+                Some(c) => out.push(c),
+                None => return Err(self.error("Unterminated string".to_string())),
+            }
+        }
+    }
+
+    // the four hex digits after `\u`, and the low half of a surrogate
+    // pair if the first four were the high half
+    fn unicode_escape(&mut self) -> Result<char, JsonError> {
+        let high = self.hex4()?;
+        let code = if (0xd800..0xdc00).contains(&high) {
+            if self.next() != Some('\\') || self.next() != Some('u') {
+                return Err(self.error("Unpaired surrogate in string".to_string()));
+            }
+            let low = self.hex4()?;
+            if !(0xdc00..0xe000).contains(&low) {
+                return Err(self.error("Unpaired surrogate in string".to_string()));
+            }
+            0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00)
+        } else {
+            high
+        };
+        char::from_u32(code).ok_or_else(|| self.error("Invalid unicode escape".to_string()))
+    }
+
+    fn hex4(&mut self) -> Result<u32, JsonError> {
+        let mut code = 0;
+        for _ in 0..4 {
+            let digit = self.next().and_then(|c| c.to_digit(16));
+            match digit {
+                Some(digit) => code = code * 16 + digit,
+                None => return Err(self.error("Invalid unicode escape".to_string())),
+            }
+        }
+        Ok(code)
+    }
+
+    fn number(&mut self) -> Result<Value, JsonError> {
+        let mut text = String::new();
+        while let Some(&c) = self.chars.peek() {
+            if !matches!(c, '-' | '+' | '.' | 'e' | 'E' | '0'..='9') {
+                break;
+            }
+            text.push(c);
+            self.next();
+        }
+        // rust's float parser is more lenient than json about things like
+        // leading zeros, that's fine for reading trees. json numbers are
+        // always finite, so one too big for an f64 is an error
+        match text.parse::<f64>() {
+            Ok(n) if n.is_finite() => Ok(Value::Number(n)),
+            Ok(_) => Err(self.error(format!("Number `{text}` is too large"))),
+            Err(_) => Err(self.error(format!("Invalid number `{text}`"))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, Value, MAX_DEPTH};
+
+    #[test]
+    fn numbers_are_finite() {
+        assert!(matches!(parse("1.5e308"), Ok(Value::Number(n)) if n == 1.5e308));
+        assert_eq!(
+            parse("[1,\n-1e999]").unwrap_err().message,
+            "Number `-1e999` is too large"
+        );
+        assert_eq!(parse("1e999").unwrap_err().line, 1);
+    }
+
+    #[test]
+    fn deep_nesting_is_an_error_not_a_stack_overflow() {
+        let deep = "[".repeat(20_000);
+        assert_eq!(parse(&deep).unwrap_err().code(), "E0003");
+
+        let limit = format!("{}{}", "[".repeat(MAX_DEPTH), "]".repeat(MAX_DEPTH));
+        assert!(matches!(parse(&limit), Ok(Value::Array(_))));
+        let over = format!("[{limit}]");
+        assert!(parse(&over).is_err());
+    }
+}
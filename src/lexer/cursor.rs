@@ -1,25 +1,42 @@
-use std::str::Chars;
+use std::collections::VecDeque;
+use std::io;
 
 // using implementation of cursor based on cursor from rust's lexer
 // (rust/compiler/rustc_lexer/src/cursor.rs)
+//
+// unlike rustc's cursor we can't clone a `Chars` to look ahead, since the
+// input may be coming from an io::Read, so we keep a small lookahead buffer
+// of the chars we have peeked at but not yet consumed
 
-pub struct Cursor<'a> {
-    initial_len: usize,
-    chars: Chars<'a>,
+pub struct Cursor<I: Iterator<Item = io::Result<char>>> {
+    chars: I,
+    lookahead: VecDeque<char>,
+    len_consumed: u32,
+    // absolute byte offset and 1-based column of the next char
+    pos: usize,
+    column: u32,
     #[cfg(debug_assertions)]
     prev: char,
     pub line: u32,
+    // first io error hit while reading, we treat it as eof
+    // and leave it to the caller to report
+    pub error: Option<io::Error>,
 }
 
 pub const EOF_CHAR: char = '\0';
 
-impl<'a> Cursor<'a> {
-    pub fn new(input: &'a str) -> Cursor<'a> {
+impl<I: Iterator<Item = io::Result<char>>> Cursor<I> {
+    pub fn new(chars: I) -> Cursor<I> {
         Cursor {
-            initial_len: input.len(),
-            chars: input.chars(),
+            chars,
+            lookahead: VecDeque::with_capacity(2),
+            len_consumed: 0,
+            pos: 0,
+            column: 1,
+            #[cfg(debug_assertions)]
             prev: EOF_CHAR,
             line: 1,
+            error: None,
         }
     }
 
@@ -35,36 +52,67 @@ impl<'a> Cursor<'a> {
         }
     }
 
+    // pull chars from the input until we have n chars of lookahead
+    // or the input is exhausted
+    fn fill(&mut self, n: usize) {
+        while self.lookahead.len() < n && self.error.is_none() {
+            match self.chars.next() {
+                Some(Ok(c)) => self.lookahead.push_back(c),
+                Some(Err(e)) => self.error = Some(e),
+                None => break,
+            }
+        }
+    }
+
     // get first char in chars without consuming
-    pub fn first(&self) -> char {
-        self.chars.clone().next().unwrap_or(EOF_CHAR)
+    pub fn first(&mut self) -> char {
+        self.fill(1);
+        self.lookahead.front().copied().unwrap_or(EOF_CHAR)
     }
 
     // get second char in chars without consuming
-    pub fn second(&self) -> char {
-        let mut iter = self.chars.clone();
-        iter.next();
-        iter.next().unwrap_or(EOF_CHAR)
+    pub fn second(&mut self) -> char {
+        self.fill(2);
+        self.lookahead.get(1).copied().unwrap_or(EOF_CHAR)
     }
 
     // return if we have reached end of file
-    pub fn is_eof(&self) -> bool {
-        self.chars.as_str().is_empty()
+    pub fn is_eof(&mut self) -> bool {
+        self.fill(1);
+        self.lookahead.is_empty()
     }
 
     // return numbers of bytes consumed so far
     pub fn len_consumed(&self) -> u32 {
-        (self.initial_len - self.chars.as_str().len()) as u32
+        self.len_consumed
     }
 
     // reset the number of bytes consumed to 0
     pub fn reset_len_consumed(&mut self) {
-        self.initial_len = self.chars.as_str().len()
+        self.len_consumed = 0
+    }
+
+    // return byte offset of the next char from the start of the input
+    pub fn pos(&self) -> usize {
+        self.pos
+    }
+
+    // return column of the next char, counted in chars from 1
+    pub fn column(&self) -> u32 {
+        self.column
     }
 
     // moves to next char acter
     pub fn advance(&mut self) -> Option<char> {
-        let c = self.chars.next()?;
+        self.fill(1);
+        let c = self.lookahead.pop_front()?;
+        self.len_consumed += c.len_utf8() as u32;
+        self.pos += c.len_utf8();
+        if c == '\n' {
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
         #[cfg(debug_assertions)]
         {
             self.prev = c;
@@ -75,8 +123,140 @@ impl<'a> Cursor<'a> {
 
     // eats symbols while predicate is true or until eof is reached
     pub fn eat_while(&mut self, mut predicate: impl FnMut(char) -> bool) {
-        while predicate(self.first()) && !self.is_eof() {
+        while !self.is_eof() && predicate(self.first()) {
             self.advance();
         }
     }
 }
+
+// decodes utf-8 chars from a buffered reader one at a time, so the
+// lexer never needs more than a char or two of the input in memory
+pub struct ReadChars<R: io::BufRead> {
+    reader: R,
+}
+
+impl<R: io::BufRead> ReadChars<R> {
+    pub fn new(reader: R) -> ReadChars<R> {
+        ReadChars { reader }
+    }
+
+    fn next_byte(&mut self) -> io::Result<Option<u8>> {
+        let byte = match self.reader.fill_buf()? {
+            [] => return Ok(None),
+            buf => buf[0],
+        };
+        self.reader.consume(1);
+        Ok(Some(byte))
+    }
+
+    fn invalid_utf8() -> io::Error {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            "stream did not contain valid UTF-8",
+        )
+    }
+}
+
+impl<R: io::BufRead> Iterator for ReadChars<R> {
+    type Item = io::Result<char>;
+
+    fn next(&mut self) -> Option<io::Result<char>> {
+        let first = match self.next_byte() {
+            Ok(Some(b)) => b,
+            Ok(None) => return None,
+            Err(e) => return Some(Err(e)),
+        };
+        let width = match first {
+            0x00..=0x7f => return Some(Ok(first as char)),
+            0xc0..=0xdf => 2,
+            0xe0..=0xef => 3,
+            0xf0..=0xf7 => 4,
+            _ => return Some(Err(Self::invalid_utf8())),
+        };
+        let mut bytes = [first, 0, 0, 0];
+        for byte in bytes.iter_mut().take(width).skip(1) {
+            match self.next_byte() {
+                Ok(Some(b)) => *byte = b,
+                Ok(None) => return Some(Err(Self::invalid_utf8())),
+                Err(e) => return Some(Err(e)),
+            }
+        }
+        match std::str::from_utf8(&bytes[..width]) {
+            Ok(s) => s.chars().next().map(Ok),
+            Err(_) => Some(Err(Self::invalid_utf8())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ReadChars;
+    use std::io::{self, BufReader};
+
+    // decode bytes read `capacity` at a time, so any multi-byte char
+    // wider than that is split across reads
+    fn decode(bytes: &[u8], capacity: usize) -> Vec<Result<char, io::ErrorKind>> {
+        ReadChars::new(BufReader::with_capacity(capacity, bytes))
+            .map(|c| c.map_err(|e| e.kind()))
+            .collect()
+    }
+
+    fn chars(s: &str) -> Vec<Result<char, io::ErrorKind>> {
+        s.chars().map(Ok).collect()
+    }
+
+    #[test]
+    fn chars_split_across_reads() {
+        // one, two, three and four byte chars
+        let text = "a é € 😀 \"ünïcödé\" // コメント\n";
+        for capacity in 1..=8 {
+            assert_eq!(decode(text.as_bytes(), capacity), chars(text), "{capacity}");
+        }
+        assert_eq!(decode(b"", 1), vec![]);
+    }
+
+    #[test]
+    fn truncated_sequences() {
+        for bytes in [&b"a\xc3"[..], b"a\xe2\x82", b"a\xf0\x9f\x98"] {
+            for capacity in [1, 2, 64] {
+                assert_eq!(
+                    decode(bytes, capacity),
+                    vec![Ok('a'), Err(io::ErrorKind::InvalidData)],
+                    "{bytes:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_lead_bytes() {
+        // a lone continuation byte, and bytes that never start a char
+        for lead in [0x80, 0xbf, 0xf8, 0xff] {
+            assert_eq!(
+                decode(&[b'a', lead, b'b'], 1),
+                vec![Ok('a'), Err(io::ErrorKind::InvalidData), Ok('b')],
+                "{lead:#x}"
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_sequences() {
+        for bytes in [
+            // a lead byte followed by something that isn't a continuation
+            &b"\xe2\x28\xa1"[..],
+            // an overlong encoding of '\0'
+            b"\xc0\x80",
+            // a utf-16 surrogate
+            b"\xed\xa0\x80",
+            // past U+10FFFF
+            b"\xf4\x90\x80\x80",
+        ] {
+            assert_eq!(
+                decode(bytes, 1).first(),
+                Some(&Err(io::ErrorKind::InvalidData)),
+                "{bytes:?}"
+            );
+        }
+    }
+}
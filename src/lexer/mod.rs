@@ -1,37 +1,69 @@
 pub mod cursor;
 pub mod token;
-use cursor::Cursor;
-use token::{Literal, Token, TokenKind};
+use cursor::{Cursor, ReadChars};
+use token::{Literal, Span, Token, TokenKind};
 
 use std::collections::HashMap;
+use std::io;
 
 pub fn scan_tokens(input: &str) -> impl Iterator<Item = Token> + '_ {
-    let mut cursor = Cursor::new(input);
+    // reading from a str can't fail, so there are no errors to unwrap
+    scan(Cursor::new(input.chars().map(Ok)), false).map_while(Result::ok)
+}
+
+// like scan_tokens, but comments come through as `Comment` and
+// `BlockComment` tokens instead of being dropped, for tools that care
+// about what the user wrote and not just what it means
+pub fn scan_tokens_with_comments(input: &str) -> impl Iterator<Item = Token> + '_ {
+    scan(Cursor::new(input.chars().map(Ok)), true).map_while(Result::ok)
+}
+
+// scan tokens lazily from any buffered reader, only pulling as much input
+// as is needed to produce the next token. if reading fails the error is
+// yielded once and the iteration ends
+pub fn scan_tokens_from_reader<R: io::BufRead>(
+    reader: R,
+) -> impl Iterator<Item = io::Result<Token>> {
+    scan(Cursor::new(ReadChars::new(reader)), false)
+}
+
+fn scan<I: Iterator<Item = io::Result<char>>>(
+    mut cursor: Cursor<I>,
+    keep_comments: bool,
+) -> impl Iterator<Item = io::Result<Token>> {
     // is_true_end is used to allow us to discover when EOF is in our input,
     // return EOF, and then kill the iteration on the next call to scan_tokens
     let mut is_true_end = false;
     std::iter::from_fn(move || {
         if is_true_end {
-            None
-        } else if cursor.is_eof() {
-            is_true_end = true;
-            Some(Token::new(TokenKind::EOF, "".to_string(), cursor.line))
-        } else {
-            cursor.reset_len_consumed();
-            let mut ret = cursor.scan_token();
-            while ret.is_none() {
-                if cursor.is_eof() {
-                    return None;
-                } else {
-                    ret = cursor.scan_token();
+            return None;
+        }
+        cursor.reset_len_consumed();
+        // skip over whitespace and comments, which don't produce a token
+        while !cursor.is_eof() {
+            let (start, line, column) = (cursor.pos(), cursor.line, cursor.column());
+            if let Some(token) = cursor.scan_token() {
+                if !keep_comments && is_comment(&token) {
+                    continue;
                 }
+                let span = Span::new(start, cursor.pos(), line, column);
+                return Some(Ok(token.span(span)));
+            }
+        }
+        is_true_end = true;
+        match cursor.error.take() {
+            Some(e) => Some(Err(e)),
+            None => {
+                let span = Span::new(cursor.pos(), cursor.pos(), cursor.line, cursor.column());
+                Some(Ok(
+                    Token::new(TokenKind::EOF, "".to_string(), cursor.line).span(span)
+                ))
             }
-            ret
         }
     })
 }
 
-impl Cursor<'_> {
+impl<I: Iterator<Item = io::Result<char>>> Cursor<I> {
     fn advance_if_next(&mut self, c: char) -> bool {
         if self.is_eof() {
             false
@@ -60,6 +92,8 @@ impl Cursor<'_> {
             '+' => Some(Token::new(Plus, "+".to_string(), self.line)),
             '*' => Some(Token::new(Star, "*".to_string(), self.line)),
             ';' => Some(Token::new(Semicolon, ";".to_string(), self.line)),
+            '?' => Some(Token::new(Question, "?".to_string(), self.line)),
+            ':' => Some(Token::new(Colon, ":".to_string(), self.line)),
 
             // optionally two char lexemes
             '!' => {
@@ -94,12 +128,19 @@ impl Cursor<'_> {
             // potential multi char comment
             '/' => {
                 if self.advance_if_next('/') {
-                    self.eat_while(|c| c != '\n');
-                    None
+                    let mut literal = std::string::String::from("//");
+                    self.eat_while(|c| {
+                        if c != '\n' {
+                            literal.push(c);
+                            true
+                        } else {
+                            false
+                        }
+                    });
+                    Some(Token::new(Comment, literal, self.line))
                 } else if self.advance_if_next('*') {
                     let literal = std::string::String::from("/*");
-                    self.block_comment(literal);
-                    None
+                    Some(self.block_comment(literal))
                 } else {
                     Some(Token::new(Slash, "/".to_string(), self.line))
                 }
@@ -167,8 +208,10 @@ impl Cursor<'_> {
             }
         });
 
-        if self.advance_if_next('.') && is_digit(self.second()) {
+        // only take the `.` if there are digits after it
+        if self.first() == '.' && is_digit(self.second()) {
             // consume fractional part as well
+            self.advance();
             literal.push('.');
             self.eat_while(|c| {
                 if is_digit(c) {
@@ -235,12 +278,28 @@ impl Cursor<'_> {
     }
 }
 
+// comments are dropped by `scan`, unless they never end
+// and we need to hand the error back
+pub fn is_comment(token: &Token) -> bool {
+    match token.kind {
+        TokenKind::Comment => true,
+        TokenKind::BlockComment => !matches!(
+            token.literal,
+            Some(Literal::Str {
+                terminated: false,
+                ..
+            })
+        ),
+        _ => false,
+    }
+}
+
 fn is_digit(c: char) -> bool {
-    c >= '0' && c <= '9'
+    c.is_ascii_digit()
 }
 
 fn is_alpha(c: char) -> bool {
-    (c >= 'a' && c <= 'z') || (c >= 'A' && c <= 'Z') || c == '_'
+    c.is_ascii_alphabetic() || c == '_'
 }
 
 fn is_alpha_numeric(c: char) -> bool {
@@ -270,9 +329,9 @@ thread_local! { static KEYWORD_MAP: HashMap<&'static str, TokenKind> =
 // not sure that there is a way to have a static
 // hashmap in rust using std, so instead will
 // implement a thread safe global variable
-fn get_text_type(s: &String) -> TokenKind {
+fn get_text_type(s: &str) -> TokenKind {
     // implement a singleton
-    KEYWORD_MAP.with(|map_cell| match map_cell.get(s.as_str()) {
+    KEYWORD_MAP.with(|map_cell| match map_cell.get(s) {
         Some(&token_kind) => token_kind,
         None => TokenKind::Identifier,
     })
@@ -284,3 +343,33 @@ pass through and check for error tokens
 if there are error tokens present, we will report them
 and stop program, else we'll continue
 */
+
+#[cfg(test)]
+mod tests {
+    use super::{scan_tokens, scan_tokens_from_reader};
+    use crate::lexer::token::Token;
+    use std::io::{self, BufReader};
+
+    #[test]
+    fn reading_gives_the_same_tokens_as_a_str() {
+        let source = "// comment\n(1 + 2.5) * -\"ünï\ncödé\" /* 😀 */ >= nil ? true : \"\" , !x\n\"unterminated";
+        let expected: Vec<Token> = scan_tokens(source).collect();
+        for capacity in [1, 2, 3, 7, 4096] {
+            let read: Vec<Token> =
+                scan_tokens_from_reader(BufReader::with_capacity(capacity, source.as_bytes()))
+                    .collect::<io::Result<_>>()
+                    .unwrap();
+            assert_eq!(read, expected, "{capacity}");
+        }
+    }
+
+    #[test]
+    fn invalid_utf8_ends_the_tokens_with_an_error() {
+        let mut tokens = scan_tokens_from_reader(&b"1 + \xff 2"[..]);
+        assert_eq!(tokens.next().unwrap().unwrap().lexeme, "1");
+        assert_eq!(tokens.next().unwrap().unwrap().lexeme, "+");
+        let err = tokens.next().unwrap().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(tokens.next().is_none());
+    }
+}
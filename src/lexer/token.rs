@@ -1,15 +1,78 @@
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Literal {
     Str { val: String, terminated: bool },
     Num { val: f64 },
+    // only made by the parser, for the `true`, `false` and `nil` keywords
+    Bool { val: bool },
+    Nil,
+}
+
+impl Literal {
+    // lox truthiness, `false` and `nil` are false and everything else is true
+    pub fn is_truthy(&self) -> bool {
+        !matches!(self, Literal::Bool { val: false } | Literal::Nil)
+    }
+
+    // lox `==`, values of different types are never equal
+    pub fn is_equal(&self, other: &Literal) -> bool {
+        match (self, other) {
+            (Literal::Num { val: a }, Literal::Num { val: b }) => a == b,
+            (Literal::Str { val: a, .. }, Literal::Str { val: b, .. }) => a == b,
+            (Literal::Bool { val: a }, Literal::Bool { val: b }) => a == b,
+            (Literal::Nil, Literal::Nil) => true,
+            _ => false,
+        }
+    }
+}
+
+// a range of the source, as byte offsets [start, end) along with the
+// line and column (counted in chars) that the range starts on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: u32,
+    pub column: u32,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize, line: u32, column: u32) -> Span {
+        Span {
+            start,
+            end,
+            line,
+            column,
+        }
+    }
+
+    // smallest span covering both self and other
+    pub fn to(self, other: Span) -> Span {
+        let (first, last) = if self.start <= other.start {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        Span {
+            end: first.end.max(last.end),
+            ..first
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"start\":{},\"end\":{},\"line\":{},\"column\":{}}}",
+            self.start, self.end, self.line, self.column
+        )
+    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Token {
     pub kind: TokenKind,
     pub lexeme: String,
     pub line: u32,
     pub literal: Option<Literal>,
+    pub span: Span,
 }
 
 impl Token {
@@ -19,17 +82,41 @@ impl Token {
             lexeme,
             line,
             literal: None,
+            span: Span::default(),
         }
     }
 
     pub fn literal(self, literal: Literal) -> Token {
         Token {
-            kind: self.kind,
-            lexeme: self.lexeme,
-            line: self.line,
             literal: Some(literal),
+            ..self
         }
     }
+
+    pub fn span(self, span: Span) -> Token {
+        Token { span, ..self }
+    }
+}
+
+impl Token {
+    // single line json object describing the token, used by `tokens --json`
+    pub fn to_json(&self) -> String {
+        let literal = match &self.literal {
+            Some(Literal::Str { val, .. }) => crate::json::quote(val),
+            Some(Literal::Num { val }) => crate::json::number(*val),
+            Some(Literal::Bool { val }) => val.to_string(),
+            Some(Literal::Nil) => "null".to_string(),
+            None => "null".to_string(),
+        };
+        format!(
+            "{{\"kind\":{},\"lexeme\":{},\"literal\":{},\"line\":{},\"span\":{}}}",
+            crate::json::quote(&self.kind.to_string()),
+            crate::json::quote(&self.lexeme),
+            literal,
+            self.line,
+            self.span.to_json()
+        )
+    }
 }
 
 impl std::fmt::Display for Token {
@@ -47,6 +134,8 @@ impl std::fmt::Display for Token {
                             terminated: t,
                         } => format!("{} {}", if *t { "terminated" } else { "unterminated" }, s),
                         Literal::Num { val: n } => n.to_string(),
+                        Literal::Bool { val: b } => b.to_string(),
+                        Literal::Nil => "nil".to_string(),
                     }
                 }
                 None => {
@@ -57,7 +146,7 @@ impl std::fmt::Display for Token {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum TokenKind {
     // single character tokens
     LeftParen,
@@ -71,6 +160,8 @@ pub enum TokenKind {
     Semicolon,
     Slash,
     Star,
+    Question,
+    Colon,
 
     // one or two character tokens
     Bang,
@@ -118,6 +209,89 @@ pub enum TokenKind {
     EOF,
 }
 
+impl TokenKind {
+    pub const ALL: &'static [TokenKind] = &[
+        TokenKind::LeftParen,
+        TokenKind::RightParen,
+        TokenKind::LeftBrace,
+        TokenKind::RightBrace,
+        TokenKind::Comma,
+        TokenKind::Dot,
+        TokenKind::Minus,
+        TokenKind::Plus,
+        TokenKind::Semicolon,
+        TokenKind::Slash,
+        TokenKind::Star,
+        TokenKind::Question,
+        TokenKind::Colon,
+        TokenKind::Bang,
+        TokenKind::BangEqual,
+        TokenKind::Equal,
+        TokenKind::EqualEqual,
+        TokenKind::Greater,
+        TokenKind::GreaterEqual,
+        TokenKind::Less,
+        TokenKind::LessEqual,
+        TokenKind::Identifier,
+        TokenKind::String,
+        TokenKind::Number,
+        TokenKind::And,
+        TokenKind::Class,
+        TokenKind::Else,
+        TokenKind::False,
+        TokenKind::Fun,
+        TokenKind::For,
+        TokenKind::If,
+        TokenKind::Nil,
+        TokenKind::Or,
+        TokenKind::Print,
+        TokenKind::Return,
+        TokenKind::Super,
+        TokenKind::This,
+        TokenKind::True,
+        TokenKind::Var,
+        TokenKind::While,
+        TokenKind::Comment,
+        TokenKind::BlockComment,
+        TokenKind::Whitespace,
+        TokenKind::Newline,
+        TokenKind::Unknown,
+        TokenKind::EOF,
+    ];
+
+    // the kind a name from the display output stands for, e.g. `Minus`
+    pub fn from_name(name: &str) -> Option<TokenKind> {
+        TokenKind::ALL
+            .iter()
+            .copied()
+            .find(|kind| kind.to_string() == name)
+    }
+
+    // how an operator is written in source, for building tokens that
+    // didn't come from the lexer
+    pub fn spelling(&self) -> Option<&'static str> {
+        use TokenKind::*;
+        let spelling = match self {
+            Comma => ",",
+            Minus => "-",
+            Plus => "+",
+            Slash => "/",
+            Star => "*",
+            Question => "?",
+            Colon => ":",
+            Bang => "!",
+            BangEqual => "!=",
+            EqualEqual => "==",
+            Greater => ">",
+            GreaterEqual => ">=",
+            Less => "<",
+            LessEqual => "<=",
+            _ => return None,
+        };
+        Some(spelling)
+    }
+}
+
 // don't bother implementing print for every enum value,
 // just pipe debug output to display
 impl std::fmt::Display for TokenKind {
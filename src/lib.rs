@@ -1,3 +1,9 @@
+pub mod analysis;
 pub mod error_handling;
+pub mod format;
+pub mod json;
 pub mod lexer;
+pub mod optimize;
+pub mod parser;
+pub mod rpn;
 pub mod syntax;
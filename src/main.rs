@@ -1,31 +1,74 @@
 use std::env;
-use std::fs;
+use std::fs::File;
 use std::io::stdin;
-use std::io::Write;
+use std::io::{BufRead, BufReader, Write};
+use std::process::ExitCode;
 
+pub mod analysis;
 pub mod error_handling;
+pub mod format;
+pub mod json;
 pub mod lexer;
+pub mod optimize;
 pub mod parser;
+pub mod rpn;
 pub mod syntax;
 
-use crate::error_handling::{CLArgsError, Error, IOError, LexError};
-use crate::lexer::scan_tokens;
-use crate::lexer::token::{Token, TokenKind};
-use crate::parser::Parser;
-use crate::syntax::expr::*;
-
-fn main() -> Result<(), Box<dyn Error>> {
-    let args: Vec<String> = env::args().collect();
-    if args.len() > 2 {
-        println!("Usage: lox_interpreter [script]");
-        return Err(Box::new(CLArgsError::new(
-            0,
-            "Incorrect commandline args".to_string(),
-        )));
-    } else if args.len() == 2 {
-        run_file(&args[1])?;
-    } else {
-        run_prompt()?;
+use crate::analysis::{LintConfig, Linter, TypeChecker};
+use crate::error_handling::{
+    codes, CLArgsError, Diagnostic, FormatError, FormatErrorKind, IOError, LoxError,
+};
+use crate::lexer::token::{Literal, Token};
+use crate::lexer::{is_comment, scan_tokens, scan_tokens_from_reader, scan_tokens_with_comments};
+use crate::optimize::ConstantFolder;
+use crate::parser::{parse, parse_cst};
+use crate::syntax::expr::{Expr, ExprFold};
+use crate::syntax::printer::{AstPrinter, DotPrinter, ReversePolishPrinter, SourcePrinter};
+
+fn main() -> ExitCode {
+    let mut args: Vec<String> = env::args().collect();
+    let mut reporter = Reporter::new(ErrorFormat::Human);
+    // --error-format can go anywhere, pull it out before looking at the rest
+    let result = match take_error_format(&mut args) {
+        Ok(format) => {
+            reporter.format = format;
+            match args.as_slice() {
+                [_] => run_prompt(&reporter),
+                [_, command, rest @ ..] if command == "tokens" => {
+                    reporter.file = script_arg(rest);
+                    dump_tokens(rest)
+                }
+                [_, command, code] if command == "explain" => explain(code),
+                [_, command, rest @ ..] if command == "ast" => {
+                    reporter.file = script_arg(rest);
+                    dump_ast(rest)
+                }
+                [_, command, rest @ ..] if command == "rpn" => {
+                    reporter.file = script_arg(rest);
+                    read_rpn(rest)
+                }
+                [_, command, rest @ ..] if command == "fmt" => {
+                    reporter.file = script_arg(rest);
+                    fmt(rest)
+                }
+                [_, command, rest @ ..] if command == "check" => {
+                    reporter.file = script_arg(rest);
+                    check(rest, &reporter)
+                }
+                [_, script] => {
+                    reporter.file = Some(script.clone());
+                    run_file(script)
+                }
+                _ => Err(usage().into()),
+            }
+        }
+        Err(err) => Err(err.into()),
+    };
+    if let Err(errors) = result {
+        reporter.report(&errors);
+        // exit with the status of the first error, that's the one
+        // everything after it may have cascaded from
+        return ExitCode::from(errors[0].exit_code() as u8);
     }
 
     // let expr = Expr::Binary(BinaryExpr {
@@ -68,19 +111,364 @@ fn main() -> Result<(), Box<dyn Error>> {
     //     })),
     // });
 
-    // let rp_printer = crate::syntax::expr::ReversePolishPrinter {};
+    // let rp_printer = crate::syntax::printer::ReversePolishPrinter {};
     // println!("{}", rp_printer.print(&expr_2));
 
+    ExitCode::SUCCESS
+}
+
+fn usage() -> LoxError {
+    println!("Usage: lox_interpreter [--error-format=human|json] [script]");
+    println!("       lox_interpreter tokens [--json] [script]");
+    println!("       lox_interpreter ast [--optimized] [--from-json] [--format=sexpr|source|dot|json|rpn|cst] [script]");
+    println!("       lox_interpreter rpn [--eval] [script]");
+    println!("       lox_interpreter fmt [--check] [--width=<columns>] [script]");
+    println!("       lox_interpreter check [script]");
+    println!("       lox_interpreter explain <error code>");
+    LoxError::Usage(CLArgsError::new(
+        0,
+        "Incorrect commandline args".to_string(),
+    ))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ErrorFormat {
+    Human,
+    Json,
+}
+
+// remove `--error-format=<format>` from the args, if it's there
+fn take_error_format(args: &mut Vec<String>) -> Result<ErrorFormat, LoxError> {
+    let mut format = ErrorFormat::Human;
+    let mut result = Ok(());
+    args.retain(|arg| match arg.strip_prefix("--error-format=") {
+        Some(value) => {
+            match value {
+                "human" => format = ErrorFormat::Human,
+                "json" => format = ErrorFormat::Json,
+                _ => result = Err(usage()),
+            }
+            false
+        }
+        None => true,
+    });
+    result.map(|_| format)
+}
+
+// the script a subcommand was pointed at, i.e. its last non flag argument
+fn script_arg(args: &[String]) -> Option<String> {
+    args.last().filter(|arg| !arg.starts_with("--")).cloned()
+}
+
+// writes diagnostics to stderr, either for people to read or as one json
+// object per line for tools
+struct Reporter {
+    format: ErrorFormat,
+    file: Option<String>,
+}
+
+impl Reporter {
+    fn new(format: ErrorFormat) -> Reporter {
+        Reporter { format, file: None }
+    }
+
+    fn report(&self, errors: &[LoxError]) {
+        let diagnostics: Vec<Diagnostic> = errors.iter().map(Diagnostic::from).collect();
+        self.report_diagnostics(&diagnostics);
+    }
+
+    fn report_diagnostics(&self, diagnostics: &[Diagnostic]) {
+        match self.format {
+            ErrorFormat::Json => {
+                for diagnostic in diagnostics {
+                    eprintln!("{}", diagnostic.to_json(self.file.as_deref()));
+                }
+            }
+            ErrorFormat::Human => {
+                for diagnostic in diagnostics {
+                    eprintln!("{diagnostic}");
+                }
+                if let Some(d) = diagnostics
+                    .iter()
+                    .find(|d| codes::explain(d.code).is_some())
+                {
+                    eprintln!(
+                        "For more information about this {}, try `lox_interpreter explain {}`.",
+                        d.severity, d.code
+                    );
+                }
+            }
+        }
+    }
+}
+
+fn open_script(script: &str) -> Result<File, LoxError> {
+    File::open(script).map_err(|x| LoxError::Io(IOError::new(0, x)))
+}
+
+// print every token the lexer produces for a script (or stdin), one per
+// line, either in the token's display format or as json lines
+fn dump_tokens(args: &[String]) -> Result<(), Vec<LoxError>> {
+    let (json, script) = match args {
+        [] => (false, None),
+        [flag] if flag == "--json" => (true, None),
+        [script] => (false, Some(script)),
+        [flag, script] if flag == "--json" => (true, Some(script)),
+        _ => return Err(usage().into()),
+    };
+    let reader: Box<dyn BufRead> = match script {
+        Some(script) => Box::new(BufReader::new(open_script(script)?)),
+        None => Box::new(stdin().lock()),
+    };
+    let mut line = 0;
+    for token in scan_tokens_from_reader(reader) {
+        match token {
+            Ok(token) => {
+                line = token.line;
+                if json {
+                    println!("{}", token.to_json());
+                } else {
+                    println!("{}", token);
+                }
+            }
+            Err(x) => return Err(LoxError::Io(IOError::new(line, x)).into()),
+        }
+    }
+    Ok(())
+}
+
+// print the extended explanation of an error code, like `rustc --explain`
+fn explain(code: &str) -> Result<(), Vec<LoxError>> {
+    match codes::explain(code) {
+        Some(explanation) => {
+            print!("{}", explanation);
+            Ok(())
+        }
+        None => Err(LoxError::Usage(CLArgsError::new(
+            0,
+            format!("`{}` is not a known error code", code),
+        ))
+        .into()),
+    }
+}
+
+// parse a script (or stdin) and check it without running it. type errors
+// that are sure to happen at runtime fail the check, lint warnings don't
+fn check(args: &[String], reporter: &Reporter) -> Result<(), Vec<LoxError>> {
+    let mut reader: Box<dyn BufRead> = match args {
+        [] => Box::new(stdin().lock()),
+        [script] => Box::new(BufReader::new(open_script(script)?)),
+        _ => return Err(usage().into()),
+    };
+    // directives live in comments, so this needs the whole source up front
+    let mut source = String::new();
+    if let Err(x) = reader.read_to_string(&mut source) {
+        return Err(LoxError::Io(IOError::new(0, x)).into());
+    }
+    let (comments, tokens): (Vec<Token>, Vec<Token>) =
+        scan_tokens_with_comments(&source).partition(is_comment);
+    let expr = parse(tokens)?;
+    let (config, mut warnings) = LintConfig::from_comments(&comments);
+    warnings.extend(Linter::new(config).check(&expr));
+    reporter.report_diagnostics(&warnings);
+    let (_, type_errors) = TypeChecker::new().check(&expr);
+    if !type_errors.is_empty() {
+        return Err(type_errors.into_iter().map(LoxError::Type).collect());
+    }
+    Ok(())
+}
+
+fn run_file(s: &str) -> Result<(), Vec<LoxError>> {
+    let file = open_script(s)?;
+    let expr = parse_reader(BufReader::new(file))?;
+    let printer = AstPrinter {};
+    println!("{}", printer.print(&expr));
+    Ok(())
+}
+
+// print the tree a script (or stdin) parses to, after the optimizer has
+// been over it with --optimized
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AstFormat {
+    Sexpr,
+    Source,
+    Dot,
+    Json,
+    Rpn,
+    Cst,
+}
+
+fn dump_ast(args: &[String]) -> Result<(), Vec<LoxError>> {
+    let mut optimized = false;
+    let mut from_json = false;
+    let mut format = AstFormat::Sexpr;
+    let mut script = None;
+    for arg in args {
+        match arg.as_str() {
+            "--optimized" => optimized = true,
+            "--from-json" => from_json = true,
+            "--format=sexpr" => format = AstFormat::Sexpr,
+            "--format=source" => format = AstFormat::Source,
+            "--format=dot" => format = AstFormat::Dot,
+            "--format=json" => format = AstFormat::Json,
+            "--format=rpn" => format = AstFormat::Rpn,
+            "--format=cst" => format = AstFormat::Cst,
+            _ if arg.starts_with("--") => return Err(usage().into()),
+            _ if script.is_none() => script = Some(arg),
+            _ => return Err(usage().into()),
+        }
+    }
+    let mut reader: Box<dyn BufRead> = match script {
+        Some(script) => Box::new(BufReader::new(open_script(script)?)),
+        None => Box::new(stdin().lock()),
+    };
+    // the concrete tree is only ever parsed from source, as it was written
+    if format == AstFormat::Cst {
+        if optimized || from_json {
+            return Err(usage().into());
+        }
+        let mut source = String::new();
+        if let Err(x) = reader.read_to_string(&mut source) {
+            return Err(LoxError::Io(IOError::new(0, x)).into());
+        }
+        print!("{}", parse_cst(&source)?.outline());
+        return Ok(());
+    }
+    // with --from-json the input is a tree someone else made, in the
+    // format `--format=json` writes, instead of lox source
+    let mut expr = if from_json {
+        let mut source = String::new();
+        if let Err(x) = reader.read_to_string(&mut source) {
+            return Err(LoxError::Io(IOError::new(0, x)).into());
+        }
+        syntax::json::from_json(&source).map_err(LoxError::from)?
+    } else {
+        parse_reader(reader)?
+    };
+    if optimized {
+        expr = ConstantFolder.fold_expr(expr);
+    }
+    match format {
+        AstFormat::Sexpr => println!("{}", AstPrinter {}.print(&expr)),
+        AstFormat::Source => println!("{}", SourcePrinter.print(&expr)),
+        AstFormat::Dot => println!("{}", DotPrinter::new().print(&expr)),
+        AstFormat::Json => println!("{}", syntax::json::to_json(&expr)),
+        AstFormat::Rpn => println!("{}", ReversePolishPrinter {}.print(&expr)),
+        AstFormat::Cst => unreachable!("handled before parsing"),
+    }
     Ok(())
 }
 
-fn run_file(s: &String) -> Result<(), Box<dyn Error>> {
-    let contents = fs::read_to_string(s).expect("Was not able to read in file {s}");
-    // let cursor = Cursor::new(contents.as_str());
-    run(contents.as_str())
+// format a script in place, or stdin to stdout. with --check nothing is
+// written, it's an error if formatting would change anything
+fn fmt(args: &[String]) -> Result<(), Vec<LoxError>> {
+    let mut check = false;
+    let mut width = format::DEFAULT_WIDTH;
+    let mut script = None;
+    for arg in args {
+        match arg.as_str() {
+            "--check" => check = true,
+            _ if arg.starts_with("--width=") => match arg["--width=".len()..].parse() {
+                Ok(columns) if columns > 0 => width = columns,
+                _ => return Err(usage().into()),
+            },
+            _ if arg.starts_with("--") => return Err(usage().into()),
+            _ if script.is_none() => script = Some(arg),
+            _ => return Err(usage().into()),
+        }
+    }
+    let mut reader: Box<dyn BufRead> = match script {
+        Some(script) => Box::new(BufReader::new(open_script(script)?)),
+        None => Box::new(stdin().lock()),
+    };
+    let mut source = String::new();
+    if let Err(x) = reader.read_to_string(&mut source) {
+        return Err(LoxError::Io(IOError::new(0, x)).into());
+    }
+    let formatted = format::format_source(&source, width)?;
+    if check {
+        if formatted != source {
+            // report the first line that would change
+            let line = source
+                .lines()
+                .zip(formatted.lines())
+                .position(|(a, b)| a != b)
+                .unwrap_or_else(|| source.lines().count().min(formatted.lines().count()));
+            let err = FormatError::new(FormatErrorKind::Unformatted, line as u32 + 1);
+            return Err(LoxError::from(err).into());
+        }
+        return Ok(());
+    }
+    match script {
+        Some(script) if formatted != source => {
+            if let Err(x) = std::fs::write(script, formatted) {
+                return Err(LoxError::Io(IOError::new(0, x)).into());
+            }
+        }
+        Some(_) => {}
+        None => print!("{formatted}"),
+    }
+    Ok(())
+}
+
+// read reverse polish notation, like `ast --format=rpn` writes, and print
+// the tree it describes, or with --eval the value it works out to
+fn read_rpn(args: &[String]) -> Result<(), Vec<LoxError>> {
+    let (eval, script) = match args {
+        [] => (false, None),
+        [flag] if flag == "--eval" => (true, None),
+        [script] => (false, Some(script)),
+        [flag, script] if flag == "--eval" => (true, Some(script)),
+        _ => return Err(usage().into()),
+    };
+    let mut reader: Box<dyn BufRead> = match script {
+        Some(script) => Box::new(BufReader::new(open_script(script)?)),
+        None => Box::new(stdin().lock()),
+    };
+    let mut source = String::new();
+    if let Err(x) = reader.read_to_string(&mut source) {
+        return Err(LoxError::Io(IOError::new(0, x)).into());
+    }
+    if eval {
+        match rpn::evaluate(&source)? {
+            Literal::Num { val } => println!("{val}"),
+            Literal::Str { val, .. } => println!("{val}"),
+            Literal::Bool { val } => println!("{val}"),
+            Literal::Nil => println!("nil"),
+        }
+    } else {
+        let expr = rpn::read(&source).map_err(LoxError::from)?;
+        println!("{}", AstPrinter {}.print(&expr));
+    }
+    Ok(())
+}
+
+fn parse_reader(reader: impl BufRead) -> Result<Expr, Vec<LoxError>> {
+    // stream the input through the lexer and parser instead of reading it
+    // all into memory up front. the lexer hands back io errors in line, so
+    // stash the first one and stop feeding tokens to the parser
+    let mut read_error = None;
+    let mut line = 0;
+    let tokens = scan_tokens_from_reader(reader).map_while(|token| match token {
+        Ok(token) => {
+            line = token.line;
+            Some(token)
+        }
+        Err(x) => {
+            read_error = Some(x);
+            None
+        }
+    });
+    let result = parse(tokens);
+    match read_error {
+        // anything the parser said after the input was cut short
+        // is noise, the read error is the real problem
+        Some(x) => Err(LoxError::Io(IOError::new(line, x)).into()),
+        None => result,
+    }
 }
 
-fn run_prompt() -> Result<(), Box<dyn Error>> {
+fn run_prompt(reporter: &Reporter) -> Result<(), Vec<LoxError>> {
     let mut line = 0;
     loop {
         line += 1;
@@ -88,14 +476,14 @@ fn run_prompt() -> Result<(), Box<dyn Error>> {
         std::io::stdout().flush().expect("flush failed!");
         let mut buf = String::new();
         if let Err(x) = stdin().read_line(&mut buf) {
-            return Err(Box::new(IOError::new(line, x)));
+            return Err(LoxError::Io(IOError::new(line, x)).into());
         }
         match buf.trim_end() {
             "" => break,
             input => {
-                if let Err(x) = run(input) {
-                    // report error if one occurred
-                    print!("{}", x);
+                if let Err(errors) = run(scan_tokens(input)) {
+                    // report errors and carry on with the next line
+                    reporter.report(&errors);
                 }
             }
         }
@@ -103,51 +491,9 @@ fn run_prompt() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
-fn run(input: &str) -> Result<(), Box<dyn Error>> {
-    use crate::lexer::token::TokenKind;
-    // let mut errors = vec![];
-
-    let tokens: Vec<Token> = scan_tokens(input).collect();
-    let mut parser: Parser = Parser::new(tokens);
-    let expr = parser.parse();
-    if let Err(err) = expr {
-        println!("{err}");
-    } else {
-        let printer = AstPrinter {};
-        let expr = expr.unwrap();
-        println!("{}", printer.print(&expr));
-    }
-    // for x in scan_tokens(input) {
-    //     match x.kind {
-    //         TokenKind::Unknown => {
-    //             errors.push(LexError::new("Unknown token".to_string(), x.line));
-    //         }
-    //         TokenKind::String => match x.literal.clone() {
-    //             Some(l) => match l {
-    //                 Literal::Str { terminated: t, .. } => {
-    //                     if !t {
-    //                         errors.push(LexError::new("Unterminated string".to_string(), x.line));
-    //                     } else {
-    //                         println!("{}", x);
-    //                     }
-    //                 }
-    //                 _ => {
-    //                     println!("{}", x);
-    //                 }
-    //             },
-    //             _ => {
-    //                 println!("{}", x);
-    //             }
-    //         },
-    //         _ => {
-    //             println!("{}", x);
-    //         }
-    //     }
-    // }
-    // if errors.len() > 0 {
-    //     for e in errors {
-    //         print!("{}", e);
-    //     }
-    // }
+fn run(tokens: impl Iterator<Item = Token>) -> Result<(), Vec<LoxError>> {
+    let expr = parse(tokens)?;
+    let printer = AstPrinter {};
+    println!("{}", printer.print(&expr));
     Ok(())
 }
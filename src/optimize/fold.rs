@@ -0,0 +1,273 @@
+use crate::analysis::{constant, Type, TypeChecker};
+use crate::lexer::token::TokenKind;
+use crate::syntax::expr::*;
+
+// folds operators on literals into the literal they evaluate to, so
+// `1 + 2 * 3` becomes `7`, drops grouping parens (the shape of the tree
+// already says what they did), picks the branch of a conditional whose
+// condition is a literal and removes double negation.
+//
+// anything that would fail or behave differently at runtime is left as it
+// was: operators on the wrong types stay so they still throw, and division
+// by zero isn't folded so the runtime decides what it gives. nodes that
+// aren't changed keep their ids, folded ones get fresh ids and the span of
+// everything they replaced. run it with `ConstantFolder.fold_expr(expr)`
+pub struct ConstantFolder;
+
+fn literal(value: Literal, span: Span) -> Expr {
+    Expr::Literal(LiteralExpr::new(value, span))
+}
+
+fn type_of(e: &Expr) -> Type {
+    TypeChecker::new().check(e).0
+}
+
+// the value of `left op right`, or None if it can't be worked out here
+fn fold_binary(op: TokenKind, left: &Literal, right: &Literal) -> Option<Literal> {
+    use TokenKind::*;
+    let value = match (op, left, right) {
+        (EqualEqual, l, r) => Literal::Bool { val: l.is_equal(r) },
+        (BangEqual, l, r) => Literal::Bool {
+            val: !l.is_equal(r),
+        },
+        (Plus, Literal::Str { val: a, .. }, Literal::Str { val: b, .. }) => Literal::Str {
+            val: format!("{a}{b}"),
+            terminated: true,
+        },
+        (Slash, Literal::Num { .. }, Literal::Num { val: b }) if *b == 0.0 => return None,
+        (op, Literal::Num { val: a }, Literal::Num { val: b }) => match op {
+            Plus => Literal::Num { val: a + b },
+            Minus => Literal::Num { val: a - b },
+            Star => Literal::Num { val: a * b },
+            Slash => Literal::Num { val: a / b },
+            Greater => Literal::Bool { val: a > b },
+            GreaterEqual => Literal::Bool { val: a >= b },
+            Less => Literal::Bool { val: a < b },
+            LessEqual => Literal::Bool { val: a <= b },
+            _ => return None,
+        },
+        _ => return None,
+    };
+    Some(value)
+}
+
+impl ExprFold for ConstantFolder {
+    fn fold_binary_expr(&mut self, b: BinaryExpr) -> Expr {
+        let left = self.fold_expr(*b.left);
+        let right = self.fold_expr(*b.right);
+        match (constant(&left), constant(&right)) {
+            // the left side of a comma is only there for its side
+            // effects, and literals don't have any
+            (Some(_), _) if b.operator.kind == TokenKind::Comma => return right,
+            (Some(l), Some(r)) => {
+                if let Some(value) = fold_binary(b.operator.kind, l, r) {
+                    return literal(value, b.span);
+                }
+            }
+            _ => {}
+        }
+        Expr::Binary(BinaryExpr {
+            left: Box::new(left),
+            right: Box::new(right),
+            ..b
+        })
+    }
+    fn fold_conditional_expr(&mut self, c: ConditionalExpr) -> Expr {
+        let condition = self.fold_expr(*c.condition);
+        match constant(&condition) {
+            Some(value) if value.is_truthy() => self.fold_expr(*c.then_branch),
+            Some(_) => self.fold_expr(*c.else_branch),
+            None => Expr::Conditional(ConditionalExpr {
+                condition: Box::new(condition),
+                then_branch: Box::new(self.fold_expr(*c.then_branch)),
+                else_branch: Box::new(self.fold_expr(*c.else_branch)),
+                ..c
+            }),
+        }
+    }
+    fn fold_grouping_expr(&mut self, g: GroupingExpr) -> Expr {
+        self.fold_expr(*g.expression)
+    }
+    fn fold_unary_expr(&mut self, u: UnaryExpr) -> Expr {
+        let right = self.fold_expr(*u.right);
+        let op = u.operator.kind;
+        match (op, right) {
+            (
+                TokenKind::Minus,
+                Expr::Literal(LiteralExpr {
+                    value: Literal::Num { val },
+                    ..
+                }),
+            ) => literal(Literal::Num { val: -val }, u.span),
+            (TokenKind::Bang, Expr::Literal(l)) => literal(
+                Literal::Bool {
+                    val: !l.value.is_truthy(),
+                },
+                u.span,
+            ),
+            // `--x` is `x` and `!!x` is `x` as long as x is already the
+            // type the operator converts to, otherwise it's still needed
+            // to throw or to turn x into a bool
+            (_, Expr::Unary(inner))
+                if inner.operator.kind == op && keeps_type(op, &inner.right) =>
+            {
+                *inner.right
+            }
+            (_, right) => Expr::Unary(UnaryExpr {
+                right: Box::new(right),
+                ..u
+            }),
+        }
+    }
+}
+
+fn keeps_type(op: TokenKind, e: &Expr) -> bool {
+    match op {
+        TokenKind::Minus => type_of(e) == Type::Number,
+        TokenKind::Bang => type_of(e) == Type::Bool,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ConstantFolder;
+    use crate::lexer::scan_tokens;
+    use crate::lexer::token::{Literal, Span, Token, TokenKind};
+    use crate::parser::parse;
+    use crate::rpn;
+    use crate::syntax::expr::*;
+    use crate::syntax::printer::{ReversePolishPrinter, SourcePrinter};
+
+    fn fold(source: &str) -> Expr {
+        ConstantFolder.fold_expr(parse(scan_tokens(source)).unwrap())
+    }
+
+    // what running the source gives
+    fn evaluate(source: &str) -> Literal {
+        let rpn = ReversePolishPrinter {}.print(&parse(scan_tokens(source)).unwrap());
+        rpn::evaluate(&rpn).unwrap()
+    }
+
+    fn assert_folds(cases: &[(&str, &str)]) {
+        for (source, expected) in cases {
+            assert_eq!(SourcePrinter.print(&fold(source)), *expected, "{source}");
+        }
+    }
+
+    #[test]
+    fn folds_operators_on_literals() {
+        assert_folds(&[
+            ("1 + 2 * 3", "7"),
+            ("(1 + 2) * 3", "9"),
+            ("\"a\" + \"b\"", "\"ab\""),
+            ("10 / 4 - 1", "1.5"),
+            ("1 < 2 == 2 >= 3", "false"),
+            ("1 == \"1\"", "false"),
+            ("nil != false", "true"),
+            ("-(2)", "-2"),
+            ("!nil", "true"),
+        ]);
+    }
+
+    #[test]
+    fn leaves_what_the_runtime_decides() {
+        assert_folds(&[
+            ("1 / 0", "1 / 0"),
+            ("0 / 0 == 0 / 0", "0 / 0 == 0 / 0"),
+            ("1 + \"a\"", "1 + \"a\""),
+            ("1 + 2 + \"a\"", "3 + \"a\""),
+            ("-\"a\"", "-\"a\""),
+            ("true * 2", "true * 2"),
+        ]);
+    }
+
+    #[test]
+    fn removes_double_negation_of_the_right_type() {
+        assert_folds(&[
+            ("--(1 / 0)", "1 / 0"),
+            ("!!(1 / 0 < 2)", "1 / 0 < 2"),
+            ("----(1 / 0)", "1 / 0"),
+        ]);
+    }
+
+    #[test]
+    fn keeps_double_negation_that_converts_or_throws() {
+        assert_folds(&[
+            // a bool, so `-` throws
+            ("--(1 / 0 < 2)", "--(1 / 0 < 2)"),
+            // a number, so `!!` turns it into a bool
+            ("!!(1 / 0)", "!!(1 / 0)"),
+            // either, so both are kept
+            ("--(1 / 0 < 2 ? 1 : \"a\")", "--(1 / 0 < 2 ? 1 : \"a\")"),
+            ("!!(1 / 0 < 2 ? true : nil)", "!!(1 / 0 < 2 ? true : nil)"),
+        ]);
+    }
+
+    #[test]
+    fn comma_drops_a_literal_left_side() {
+        assert_folds(&[
+            ("1, 2", "2"),
+            ("1, 2, \"a\" + \"b\"", "\"ab\""),
+            ("1 / 0, 2 + 3", "1 / 0, 5"),
+        ]);
+    }
+
+    #[test]
+    fn constant_conditions_pick_their_branch() {
+        assert_folds(&[
+            ("true ? 1 : 2", "1"),
+            ("nil ? 1 : 2", "2"),
+            ("0 ? 1 : 2", "1"),
+            ("1 < 2 ? \"yes\" : 1 / 0", "\"yes\""),
+            ("1 / 0 ? 1 + 1 : 2", "1 / 0 ? 2 : 2"),
+        ]);
+    }
+
+    // no source folds to NaN, division by zero is left alone, but a tree
+    // with NaN literals in it still has to compare them like the runtime
+    #[test]
+    fn nan_is_not_equal_to_itself() {
+        let nan = || {
+            Box::new(Expr::Literal(LiteralExpr::new(
+                Literal::Num { val: f64::NAN },
+                Span::default(),
+            )))
+        };
+        let operator = |kind: TokenKind| Token::new(kind, kind.spelling().unwrap().to_string(), 1);
+        for (kind, expected) in [(TokenKind::EqualEqual, false), (TokenKind::BangEqual, true)] {
+            let tree = Expr::Binary(BinaryExpr::new(
+                nan(),
+                operator(kind),
+                nan(),
+                Span::default(),
+            ));
+            let runtime = evaluate(&format!("0 / 0 {} 0 / 0", kind.spelling().unwrap()));
+            assert_eq!(runtime, Literal::Bool { val: expected });
+            match ConstantFolder.fold_expr(tree) {
+                Expr::Literal(l) => assert_eq!(l.value, runtime),
+                folded => panic!("{} wasn't folded", SourcePrinter.print(&folded)),
+            }
+        }
+    }
+
+    // whatever folds to a literal gives the value the program evaluates to
+    #[test]
+    fn folds_to_what_the_runtime_gives() {
+        for source in [
+            "1 + 2 * 3 - 4 / 8",
+            "\"a\" + \"b\" == \"ab\"",
+            "!(1 > 2) ? -3 : 4",
+            "1, nil",
+            "--2 <= 2",
+            "!!\"\"",
+            "(1 == 1) != (nil == false)",
+        ] {
+            let runtime = evaluate(source);
+            match fold(source) {
+                Expr::Literal(l) => assert!(l.value.is_equal(&runtime), "{source}"),
+                folded => panic!("{source} folded to {}", SourcePrinter.print(&folded)),
+            }
+        }
+    }
+}
@@ -0,0 +1,3 @@
+// passes that rewrite a parsed program into an equivalent, simpler one
+mod fold;
+pub use fold::ConstantFolder;
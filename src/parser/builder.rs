@@ -0,0 +1,207 @@
+use crate::lexer::token::{Literal, Span, Token};
+use crate::syntax::arena_expr::{self, ExprArena, ExprRef};
+use crate::syntax::cst::{CstElement, CstNode, CstToken, SyntaxKind};
+use crate::syntax::expr::{self, Expr};
+
+// how the parser puts together the nodes it recognises, so the same
+// grammar can build a boxed tree or fill in an arena
+pub trait AstBuilder {
+    type Node;
+
+    fn span(&self, node: &Self::Node) -> Span;
+    fn binary(
+        &mut self,
+        left: Self::Node,
+        operator: Token,
+        right: Self::Node,
+        span: Span,
+    ) -> Self::Node;
+    fn conditional(
+        &mut self,
+        condition: Self::Node,
+        then_branch: Self::Node,
+        else_branch: Self::Node,
+        span: Span,
+    ) -> Self::Node;
+    fn grouping(&mut self, expression: Self::Node, span: Span) -> Self::Node;
+    fn literal(&mut self, value: Literal, span: Span) -> Self::Node;
+    fn unary(&mut self, operator: Token, right: Self::Node, span: Span) -> Self::Node;
+}
+
+// builds the usual tree of boxed `Expr`s
+#[derive(Default)]
+pub struct BoxedBuilder;
+
+impl AstBuilder for BoxedBuilder {
+    type Node = Expr;
+
+    fn span(&self, node: &Expr) -> Span {
+        node.span()
+    }
+
+    fn binary(&mut self, left: Expr, operator: Token, right: Expr, span: Span) -> Expr {
+        Expr::Binary(expr::BinaryExpr::new(
+            Box::new(left),
+            operator,
+            Box::new(right),
+            span,
+        ))
+    }
+
+    fn conditional(
+        &mut self,
+        condition: Expr,
+        then_branch: Expr,
+        else_branch: Expr,
+        span: Span,
+    ) -> Expr {
+        Expr::Conditional(expr::ConditionalExpr::new(
+            Box::new(condition),
+            Box::new(then_branch),
+            Box::new(else_branch),
+            span,
+        ))
+    }
+
+    fn grouping(&mut self, expression: Expr, span: Span) -> Expr {
+        Expr::Grouping(expr::GroupingExpr::new(Box::new(expression), span))
+    }
+
+    fn literal(&mut self, value: Literal, span: Span) -> Expr {
+        Expr::Literal(expr::LiteralExpr::new(value, span))
+    }
+
+    fn unary(&mut self, operator: Token, right: Expr, span: Span) -> Expr {
+        Expr::Unary(expr::UnaryExpr::new(operator, Box::new(right), span))
+    }
+}
+
+// allocates every node into the arena and hands back its ref
+impl AstBuilder for ExprArena {
+    type Node = ExprRef;
+
+    fn span(&self, node: &ExprRef) -> Span {
+        self[*node].span()
+    }
+
+    fn binary(&mut self, left: ExprRef, operator: Token, right: ExprRef, span: Span) -> ExprRef {
+        self.alloc(arena_expr::Expr::Binary(arena_expr::BinaryExpr::new(
+            left, operator, right, span,
+        )))
+    }
+
+    fn conditional(
+        &mut self,
+        condition: ExprRef,
+        then_branch: ExprRef,
+        else_branch: ExprRef,
+        span: Span,
+    ) -> ExprRef {
+        self.alloc(arena_expr::Expr::Conditional(
+            arena_expr::ConditionalExpr::new(condition, then_branch, else_branch, span),
+        ))
+    }
+
+    fn grouping(&mut self, expression: ExprRef, span: Span) -> ExprRef {
+        self.alloc(arena_expr::Expr::Grouping(arena_expr::GroupingExpr::new(
+            expression, span,
+        )))
+    }
+
+    fn literal(&mut self, value: Literal, span: Span) -> ExprRef {
+        self.alloc(arena_expr::Expr::Literal(arena_expr::LiteralExpr::new(
+            value, span,
+        )))
+    }
+
+    fn unary(&mut self, operator: Token, right: ExprRef, span: Span) -> ExprRef {
+        self.alloc(arena_expr::Expr::Unary(arena_expr::UnaryExpr::new(
+            operator, right, span,
+        )))
+    }
+}
+
+// builds a lossless concrete syntax tree. the parser only hands over the
+// nodes it recognised and their spans, so every node takes the tokens in
+// its span that its children haven't already, which is how parens, `?`
+// and `:` end up in the right place
+pub struct CstBuilder {
+    // every token of the source, taken as the nodes claim them
+    tokens: Vec<Option<CstToken>>,
+    starts: Vec<usize>,
+}
+
+impl CstBuilder {
+    pub fn new(tokens: Vec<CstToken>) -> CstBuilder {
+        CstBuilder {
+            starts: tokens.iter().map(|token| token.token.span.start).collect(),
+            tokens: tokens.into_iter().map(Some).collect(),
+        }
+    }
+
+    fn node(&mut self, kind: SyntaxKind, span: Span, children: Vec<CstNode>) -> CstNode {
+        let first = self.starts.partition_point(|&start| start < span.start);
+        let last = self.starts.partition_point(|&start| start < span.end);
+        let mut elements: Vec<(usize, CstElement)> = children
+            .into_iter()
+            .map(|child| (child.span.start, CstElement::Node(child)))
+            .collect();
+        for token in self.tokens[first..last].iter_mut().filter_map(Option::take) {
+            elements.push((token.token.span.start, CstElement::Token(token)));
+        }
+        elements.sort_by_key(|(start, _)| *start);
+        CstNode {
+            kind,
+            span,
+            children: elements.into_iter().map(|(_, element)| element).collect(),
+        }
+    }
+
+    // the root of the tree, the expression followed by every token the
+    // parser didn't use, which is at least the end of input token
+    pub fn finish(self, expression: CstNode) -> CstNode {
+        let end = self.starts.last().copied().unwrap_or_default();
+        let mut children = vec![CstElement::Node(expression)];
+        children.extend(self.tokens.into_iter().flatten().map(CstElement::Token));
+        CstNode {
+            kind: SyntaxKind::Root,
+            span: Span::new(0, end, 1, 1),
+            children,
+        }
+    }
+}
+
+impl AstBuilder for CstBuilder {
+    type Node = CstNode;
+
+    fn span(&self, node: &CstNode) -> Span {
+        node.span
+    }
+
+    fn binary(&mut self, left: CstNode, _: Token, right: CstNode, span: Span) -> CstNode {
+        self.node(SyntaxKind::Binary, span, vec![left, right])
+    }
+
+    fn conditional(
+        &mut self,
+        condition: CstNode,
+        then_branch: CstNode,
+        else_branch: CstNode,
+        span: Span,
+    ) -> CstNode {
+        let children = vec![condition, then_branch, else_branch];
+        self.node(SyntaxKind::Conditional, span, children)
+    }
+
+    fn grouping(&mut self, expression: CstNode, span: Span) -> CstNode {
+        self.node(SyntaxKind::Grouping, span, vec![expression])
+    }
+
+    fn literal(&mut self, _: Literal, span: Span) -> CstNode {
+        self.node(SyntaxKind::Literal, span, Vec::new())
+    }
+
+    fn unary(&mut self, _: Token, right: CstNode, span: Span) -> CstNode {
+        self.node(SyntaxKind::Unary, span, vec![right])
+    }
+}
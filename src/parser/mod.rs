@@ -1,3 +1,298 @@
+mod builder;
+#[allow(clippy::module_inception)]
 mod parser;
+mod rules;
 
-pub use parser::Parser;
+pub use builder::{AstBuilder, BoxedBuilder, CstBuilder};
+pub use parser::{Parser, ParserError};
+pub use rules::{infix_binding, Associativity, Precedence};
+
+use crate::error_handling::{LexError, LoxError};
+use crate::lexer::token::{Token, TokenKind};
+use crate::lexer::{is_comment, scan_tokens_with_comments};
+use crate::syntax::cst::{CstNode, CstToken, Trivia, TriviaKind};
+use crate::syntax::expr::Expr;
+
+// parse a whole token stream into a tree, reporting lex errors the
+// tokens carry along with anything the parser finds
+pub fn parse(tokens: impl IntoIterator<Item = Token>) -> Result<Expr, Vec<LoxError>> {
+    // the lexer leaves its errors in the token stream, pull them out
+    // before the parser sees them so they're reported once, as lex errors
+    let mut errors = Vec::new();
+    let tokens = tokens
+        .into_iter()
+        .filter(|token| match LexError::from_token(token) {
+            Some(err) => {
+                errors.push(LoxError::Lex(err));
+                false
+            }
+            None => true,
+        });
+    let mut parser = Parser::new(tokens);
+    let expr = parser.parse();
+    drop(parser);
+
+    match expr {
+        Ok(expr) if errors.is_empty() => Ok(expr),
+        Ok(_) => Err(errors),
+        Err(parse_errors) => {
+            errors.extend(parse_errors.into_iter().map(LoxError::Parse));
+            Err(errors)
+        }
+    }
+}
+
+// parse source into a concrete syntax tree, which prints back out as
+// exactly the source it came from. `lower` it for the tree `parse` gives
+pub fn parse_cst(source: &str) -> Result<CstNode, Vec<LoxError>> {
+    let mut tokens = Vec::new();
+    let mut leading = Vec::new();
+    let mut end = 0;
+    for token in scan_tokens_with_comments(source) {
+        // whitespace doesn't get tokens, it's whatever is between them
+        if token.span.start > end {
+            leading.push(Trivia {
+                kind: TriviaKind::Whitespace,
+                text: source[end..token.span.start].to_string(),
+            });
+        }
+        end = token.span.end;
+        let text = source[token.span.start..token.span.end].to_string();
+        if is_comment(&token) {
+            let kind = match token.kind {
+                TokenKind::Comment => TriviaKind::Comment,
+                _ => TriviaKind::BlockComment,
+            };
+            leading.push(Trivia { kind, text });
+            continue;
+        }
+        tokens.push(CstToken {
+            leading: std::mem::take(&mut leading),
+            token,
+            text,
+        });
+    }
+
+    let errors: Vec<LoxError> = tokens
+        .iter()
+        .filter_map(|token| LexError::from_token(&token.token))
+        .map(LoxError::Lex)
+        .collect();
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    let parser_tokens: Vec<Token> = tokens.iter().map(|token| token.token.clone()).collect();
+    let mut parser = Parser::with_builder(parser_tokens, CstBuilder::new(tokens));
+    match parser.parse() {
+        Ok(expression) => Ok(parser.into_builder().finish(expression)),
+        Err(errors) => Err(errors.into_iter().map(LoxError::Parse).collect()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{parse, parse_cst};
+    use crate::lexer::scan_tokens;
+    use crate::syntax::expr::Expr;
+    use crate::syntax::printer::AstPrinter;
+    use crate::syntax::{Structural, StructuralKey};
+
+    fn parse_str(source: &str) -> Expr {
+        parse(scan_tokens(source)).unwrap()
+    }
+
+    #[test]
+    fn parses_of_the_same_source_are_structurally_equal() {
+        let first = parse_str("1 + 2 * (3 - 4) ? \"a\" : nil");
+        let second = parse_str("1+2*(3-4)?\"a\":nil");
+        assert!(first.structural_eq(&second));
+        // `==` compares node ids, and every parse gets fresh ones
+        assert_ne!(first, second);
+        assert_eq!(first, first.clone());
+    }
+
+    #[test]
+    fn different_trees_are_not_structurally_equal() {
+        let pairs = [
+            ("1 + 2", "2 + 1"),
+            ("1 - 2 - 3", "1 - (2 - 3)"),
+            ("(1)", "1"),
+            ("-1", "!1"),
+            ("\"1\"", "1"),
+            ("true ? 1 : 2", "false ? 1 : 2"),
+        ];
+        for (a, b) in pairs {
+            assert!(!parse_str(a).structural_eq(&parse_str(b)), "{a} vs {b}");
+        }
+    }
+
+    #[test]
+    fn structural_keys_find_equal_trees() {
+        let first = parse_str("1 + 2");
+        let mut seen = HashMap::new();
+        seen.insert(StructuralKey(&first), "first");
+        let again = parse_str("1   +   2");
+        assert_eq!(seen.get(&StructuralKey(&again)), Some(&"first"));
+        assert_eq!(seen.get(&StructuralKey(&parse_str("1 + 3"))), None);
+    }
+
+    // every level of parse_precedence, how it associates and how the levels
+    // nest, as the s-expression the tree prints as
+    #[test]
+    fn precedence_and_associativity() {
+        let table = [
+            ("1 + 2 * 3", "(+ 1 (* 2 3))"),
+            ("1 - 2 - 3", "(- (- 1 2) 3)"),
+            ("1 / 2 / 3 * 4", "(* (/ (/ 1 2) 3) 4)"),
+            ("(1 + 2) * 3", "(* (group (+ 1 2)) 3)"),
+            ("((1))", "(group (group 1))"),
+            ("1 + 2 == 3 + 4", "(== (+ 1 2) (+ 3 4))"),
+            ("1 < 2 == 3 >= 4", "(== (< 1 2) (>= 3 4))"),
+            ("1 <= 2 != 3 > 4", "(!= (<= 1 2) (> 3 4))"),
+            ("-1", "(- 1)"),
+            ("!!true", "(! (! true))"),
+            ("-1 * -2", "(* (- 1) (- 2))"),
+            ("!(1 > 2)", "(! (group (> 1 2)))"),
+            ("\"s\" + nil", "(+ s null)"),
+            ("1 ? 2 : 3 ? 4 : 5", "(?: 1 2 (?: 3 4 5))"),
+            ("1 ? 2 ? 3 : 4 : 5", "(?: 1 (?: 2 3 4) 5)"),
+            ("1 == 2 ? 3 : 4, 5", "(, (?: (== 1 2) 3 4) 5)"),
+            ("1 ? 2, 3 : 4", "(?: 1 (, 2 3) 4)"),
+            ("1, 2, 3", "(, (, 1 2) 3)"),
+        ];
+        for (source, expected) in table {
+            assert_eq!(AstPrinter.print(&parse_str(source)), expected, "{source}");
+        }
+    }
+
+    #[test]
+    fn incomplete_expressions_are_errors() {
+        for source in ["1 +", "(1", "? 1", "1 ? 2", ""] {
+            assert!(parse(scan_tokens(source)).is_err(), "{source}");
+        }
+    }
+
+    #[test]
+    fn trailing_tokens_are_errors() {
+        for (source, found) in [("1 + 2 3", "`3`"), ("1 ) + 2", "`)`"), ("(1) (2)", "`(`")] {
+            let errors = parse(scan_tokens(source)).unwrap_err();
+            assert_eq!(errors.len(), 1, "{source}");
+            assert_eq!(
+                errors[0].message(),
+                format!("Expected end of input but found {found}")
+            );
+        }
+    }
+
+    // the error production for a binary operator with nothing on its left
+    // reports it and carries on parsing the right operand
+    fn errors(source: &str) -> Vec<(&'static str, String, Option<usize>)> {
+        parse(scan_tokens(source))
+            .unwrap_err()
+            .iter()
+            .map(|err| (err.code(), err.message(), err.span().map(|span| span.start)))
+            .collect()
+    }
+
+    #[test]
+    fn missing_left_operands_are_reported() {
+        let missing = |start| {
+            (
+                "E0202",
+                "missing left-hand operand for `*`".to_string(),
+                Some(start),
+            )
+        };
+        assert_eq!(errors("* 3"), vec![missing(0)]);
+        assert_eq!(errors("1 + * 2"), vec![missing(4)]);
+        assert_eq!(errors("(* 2)"), vec![missing(1)]);
+    }
+
+    #[test]
+    fn parsing_goes_on_after_a_missing_left_operand() {
+        assert_eq!(
+            errors("* 1 + / 2"),
+            vec![
+                (
+                    "E0202",
+                    "missing left-hand operand for `*`".to_string(),
+                    Some(0)
+                ),
+                (
+                    "E0202",
+                    "missing left-hand operand for `/`".to_string(),
+                    Some(6)
+                ),
+            ]
+        );
+        assert_eq!(
+            errors("* (1 + 2"),
+            vec![
+                (
+                    "E0202",
+                    "missing left-hand operand for `*`".to_string(),
+                    Some(0)
+                ),
+                (
+                    "E0201",
+                    "Expected RightParen but found end of input".to_string(),
+                    Some(8)
+                ),
+            ]
+        );
+        assert_eq!(
+            errors("== 1 2"),
+            vec![
+                (
+                    "E0202",
+                    "missing left-hand operand for `==`".to_string(),
+                    Some(0)
+                ),
+                (
+                    "E0201",
+                    "Expected end of input but found `2`".to_string(),
+                    Some(5)
+                ),
+            ]
+        );
+    }
+
+    // the tree keeps everything, so it prints back as the exact source
+    // and lowers to the same tree `parse` gives
+    #[test]
+    fn cst_round_trips() {
+        for source in [
+            "1",
+            "1 + 2 * 3",
+            "  1+2  ",
+            "\n\n(1 + (2))\n",
+            "((1)) ? \"a\" : -(nil)",
+            "1 ,2, 3",
+            "// before\n1 // after 1\n+ /* in */ 2\n// at the end",
+            "/* block\n   over lines */ !true",
+            "1 +\r\n\t2\r\n",
+            "\"multi\nline\" + \"string\"",
+        ] {
+            let cst = parse_cst(source).unwrap();
+            assert_eq!(cst.to_string(), source);
+            let expected = parse_str(source);
+            let lowered = cst.lower();
+            assert!(
+                lowered.structural_eq(&expected),
+                "{source:?} lowered to {}, expected {}",
+                AstPrinter.print(&lowered),
+                AstPrinter.print(&expected)
+            );
+        }
+    }
+
+    #[test]
+    fn cst_errors_match_parse() {
+        for source in ["1 +", "(1", "\"unterminated", "1 @ 2", "1 + 2 3"] {
+            assert!(parse_cst(source).is_err(), "{source:?}");
+            assert!(parse(scan_tokens(source)).is_err(), "{source:?}");
+        }
+    }
+}
@@ -1,187 +1,242 @@
-use crate::lexer::token::{Token, TokenKind};
-use crate::syntax::expr::{BinaryExpr, Expr, GroupingExpr, Literal, LiteralExpr, UnaryExpr};
+use std::collections::VecDeque;
 
-pub struct Parser {
-    pub tokens: Vec<Token>,
-    pub current: usize,
+use super::builder::{AstBuilder, BoxedBuilder};
+use super::rules::{self, Associativity, Precedence};
+use crate::lexer::token::{Literal, Span, Token, TokenKind};
+
+// the parser pulls tokens lazily from any token iterator, only keeping the
+// previous token and a small lookahead buffer around, so parsing can begin
+// before the lexer has finished with the input. nodes are put together by
+// the builder, by default into a tree of boxed `Expr`s
+pub struct Parser<I: Iterator<Item = Token>, B: AstBuilder = BoxedBuilder> {
+    tokens: I,
+    builder: B,
+    lookahead: VecDeque<Token>,
+    previous: Option<Token>,
+    // errors we were able to recover from and keep parsing past
+    errors: Vec<ParserError>,
 }
 
+#[derive(Debug)]
 pub enum ParserError {
     UnexpectedToken(String, Token),
-    Test,
+    MissingLeftOperand(Token),
 }
 
-impl std::fmt::Debug for ParserError {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+impl ParserError {
+    pub fn code(&self) -> &'static str {
         match self {
-            ParserError::UnexpectedToken(expected_token_msg, actual_token) => {
-                write!(
-                    f,
-                    "Expected {} at line={}, got: {:?}",
-                    expected_token_msg, actual_token.line, actual_token
-                )
-            }
-            _ => {
-                write!(f, "Test")
-            }
+            ParserError::UnexpectedToken(..) => "E0201",
+            ParserError::MissingLeftOperand(..) => "E0202",
+        }
+    }
+
+    pub fn token(&self) -> &Token {
+        match self {
+            ParserError::UnexpectedToken(_, token) => token,
+            ParserError::MissingLeftOperand(token) => token,
         }
     }
+
+    pub fn span(&self) -> Span {
+        self.token().span
+    }
 }
 
 impl std::fmt::Display for ParserError {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "{:?}", self)
+        match self {
+            ParserError::UnexpectedToken(expected_token_msg, actual_token) => {
+                let found = match actual_token.kind {
+                    TokenKind::EOF => "end of input".to_string(),
+                    _ => format!("`{}`", actual_token.lexeme),
+                };
+                write!(f, "Expected {} but found {}", expected_token_msg, found)
+            }
+            ParserError::MissingLeftOperand(operator) => {
+                write!(f, "missing left-hand operand for `{}`", operator.lexeme)
+            }
+        }
     }
 }
 
+impl std::error::Error for ParserError {}
+
 // token parsing
-impl Parser {
-    pub fn parse(&mut self) -> Result<Expr, ParserError> {
-        self.expression()
+impl<I: Iterator<Item = Token>, B: AstBuilder> Parser<I, B> {
+    // parse an expression that has to be all of the input, reporting every
+    // error we ran into. errors from error productions don't stop the
+    // parse, so there may be several
+    pub fn parse(&mut self) -> Result<B::Node, Vec<ParserError>> {
+        let expr = self.expression().and_then(|expr| {
+            self.end_of_input()?;
+            Ok(expr)
+        });
+        let mut errors = std::mem::take(&mut self.errors);
+        match expr {
+            Ok(expr) if errors.is_empty() => Ok(expr),
+            Ok(_) => Err(errors),
+            Err(err) => {
+                errors.push(err);
+                Err(errors)
+            }
+        }
     }
 
-    fn expression(&mut self) -> Result<Expr, ParserError> {
-        self.equality()
+    fn expression(&mut self) -> Result<B::Node, ParserError> {
+        self.parse_precedence(Precedence::Comma)
+    }
+
+    // anything left after the expression would otherwise be silently lost
+    fn end_of_input(&mut self) -> Result<(), ParserError> {
+        match self.peek().kind {
+            TokenKind::EOF => Ok(()),
+            _ => {
+                let found_token = self.peek().clone();
+                Err(self
+                    .unexpected_token_with_expected_types(found_token, "end of input".to_string()))
+            }
+        }
     }
 
-    fn equality(&mut self) -> Result<Expr, ParserError> {
-        let mut expr = self.comparison()?;
+    // pratt parser driven by the table in `rules`. parse a prefix
+    // expression, then keep folding it into the left operand of any infix
+    // operator that binds at least as tightly as `precedence`
+    fn parse_precedence(&mut self, precedence: Precedence) -> Result<B::Node, ParserError> {
+        let prefix = match rules::rule::<I, B>(self.peek().kind).prefix {
+            Some(prefix) => prefix,
+            None => {
+                let found_token = self.peek().clone();
+                return Err(self.unexpected_token_with_expected_types(
+                    found_token,
+                    "`false`, `true`, `NUMBER`, `STRING`, or `)`".to_string(),
+                ));
+            }
+        };
+        self.advance();
+        let mut expr = prefix(self)?;
 
-        while self.match_any(&[TokenKind::BangEqual, TokenKind::EqualEqual]) {
-            let op: Token = self.prev().clone();
-            let right: Expr = self.comparison()?;
-            expr = Expr::Binary(BinaryExpr::new(Box::new(expr), op, Box::new(right)));
+        loop {
+            let rule = rules::rule::<I, B>(self.peek().kind);
+            match rule.infix {
+                Some(infix) if precedence <= rule.precedence => {
+                    self.advance();
+                    expr = infix(self, expr)?;
+                }
+                _ => break,
+            }
         }
 
         Ok(expr)
     }
 
-    fn comparison(&mut self) -> Result<Expr, ParserError> {
-        // place holder return value to quiet parser
-        let mut expr = self.term()?;
-        while self.match_any(&[
-            TokenKind::Greater,
-            TokenKind::GreaterEqual,
-            TokenKind::Less,
-            TokenKind::LessEqual,
-        ]) {
-            let op: Token = self.prev().clone();
-            let right: Expr = self.term()?;
-            expr = Expr::Binary(BinaryExpr::new(Box::new(expr), op, Box::new(right)));
+    // precedence to parse the right operand of `operator` at, left
+    // associative operators only take operands that bind tighter
+    fn right_operand_precedence(operator: TokenKind) -> Precedence {
+        let rule = rules::rule::<I, B>(operator);
+        match rule.associativity {
+            Associativity::Left => rule.precedence.next(),
+            Associativity::Right => rule.precedence,
         }
-        Ok(expr)
     }
 
-    fn term(&mut self) -> Result<Expr, ParserError> {
-        let mut expr = self.factor()?;
-        while self.match_any(&[TokenKind::Plus, TokenKind::Minus]) {
-            let op: Token = self.prev().clone();
-            let right: Expr = self.factor()?;
-            expr = Expr::Binary(BinaryExpr::new(Box::new(expr), op, Box::new(right)));
-        }
-        Ok(expr)
+    pub(super) fn binary(&mut self, left: B::Node) -> Result<B::Node, ParserError> {
+        let op: Token = self.prev().clone();
+        let right = self.parse_precedence(Self::right_operand_precedence(op.kind))?;
+        let span = self.builder.span(&left).to(self.builder.span(&right));
+        Ok(self.builder.binary(left, op, right, span))
     }
 
-    fn factor(&mut self) -> Result<Expr, ParserError> {
-        let mut expr = self.unary()?;
-        while self.match_any(&[TokenKind::Slash, TokenKind::Star]) {
-            let op: Token = self.prev().clone();
-            let right: Expr = self.unary()?;
-            expr = Expr::Binary(BinaryExpr::new(Box::new(expr), op, Box::new(right)));
-        }
-        Ok(expr)
+    // cond ? a : b, the middle operand can be any expression since it is
+    // delimited by `?` and `:`, and `?:` is right associative so the else
+    // branch may itself be a conditional
+    pub(super) fn conditional(&mut self, condition: B::Node) -> Result<B::Node, ParserError> {
+        let then_branch = self.expression()?;
+        let found_token = self.peek().clone();
+        self.consume(found_token, TokenKind::Colon)?;
+        let else_branch =
+            self.parse_precedence(Self::right_operand_precedence(TokenKind::Question))?;
+        let span = self
+            .builder
+            .span(&condition)
+            .to(self.builder.span(&else_branch));
+        Ok(self
+            .builder
+            .conditional(condition, then_branch, else_branch, span))
     }
 
-    fn unary(&mut self) -> Result<Expr, ParserError> {
-        if self.match_any(&[TokenKind::Bang, TokenKind::Minus]) {
-            let op: Token = self.prev().clone();
-            let right: Expr = self.unary()?;
-            Ok(Expr::Unary(UnaryExpr::new(op, Box::new(right))))
-        } else {
-            let ret = self.primary();
-            ret
-        }
+    pub(super) fn unary(&mut self) -> Result<B::Node, ParserError> {
+        let op: Token = self.prev().clone();
+        let right = self.parse_precedence(Precedence::Unary)?;
+        let span = op.span.to(self.builder.span(&right));
+        Ok(self.builder.unary(op, right, span))
     }
 
-    fn primary(&mut self) -> Result<Expr, ParserError> {
-        match self.peek().kind {
-            TokenKind::False => {
-                self.advance();
-                Ok(Expr::Literal(LiteralExpr::new(Literal::Str {
-                    val: "false".to_string(),
-                    terminated: true,
-                })))
-            }
-            TokenKind::True => {
-                self.advance();
-                Ok(Expr::Literal(LiteralExpr::new(Literal::Str {
-                    val: "true".to_string(),
-                    terminated: true,
-                })))
-            }
-            TokenKind::Nil => {
-                self.advance();
-                Ok(Expr::Literal(LiteralExpr::new(Literal::Str {
-                    val: "null".to_string(),
-                    terminated: true,
-                })))
-            }
-            TokenKind::Number => {
-                self.advance();
-                Ok(Expr::Literal(LiteralExpr::new(
-                    self.prev().literal.clone().unwrap(),
-                )))
-            }
-            TokenKind::String => {
-                self.advance();
-                Ok(Expr::Literal(LiteralExpr::new(
-                    self.prev().literal.clone().unwrap(),
-                )))
-            }
-            TokenKind::LeftParen => {
-                self.advance();
-                let expr: Expr = self.expression()?;
-                self.consume(self.peek().clone(), TokenKind::RightParen)?;
+    pub(super) fn grouping(&mut self) -> Result<B::Node, ParserError> {
+        let left_paren = self.prev().span;
+        let expr = self.expression()?;
+        let found_token = self.peek().clone();
+        self.consume(found_token, TokenKind::RightParen)?;
 
-                Ok(Expr::Grouping(GroupingExpr::new(Box::new(expr))))
-                // Ok(Expr::Literal(LiteralExpr::new(self.prev().literal.unwrap())))
-            }
-            _ => Err(self.unexpected_token_with_expected_types(
-                self.peek().clone(),
-                "`false`, `true`, `NUMBER`, `STRING`, or `)`".to_string(),
-            )),
-        }
-        // if self.match_any(&[TokenKind::False]) {
-        //     Expr::Literal(LiteralExpr::new(Literal::Str {
-        //         val: "false".to_string(),
-        //         terminated: true,
-        //     }))
-        // } else if self.match_any(&[TokenKind::True]) {
-        //     Expr::Literal(LiteralExpr::new(Literal::Str {
-        //         val: "true".to_string(),
-        //         terminated: true,
-        //     }))
-        // } else if self.match_any(&[TokenKind::Nil]) {
-        //     Expr::Literal(LiteralExpr::new(Literal::Str {
-        //         val: "null".to_string(),
-        //         terminated: true,
-        //     }))
-        // } else if self.match_any(&[TokenKind::Number]) {
-        //     Expr::Literal(LiteralExpr::new(self.prev().clone()))
-        // } else if self.match_any(&[TokenKind::String]) {
-        //     Expr::Literal(LiteralExpr::new(self.prev().clone()))
-        // }
+        // the group covers both parens, not just the inner expression
+        let span = left_paren.to(self.prev().span);
+        Ok(self.builder.grouping(expr, span))
+    }
+
+    pub(super) fn literal(&mut self) -> Result<B::Node, ParserError> {
+        let value = match self.prev().kind {
+            TokenKind::False => Literal::Bool { val: false },
+            TokenKind::True => Literal::Bool { val: true },
+            TokenKind::Nil => Literal::Nil,
+            _ => self.prev().literal.clone().unwrap(),
+        };
+        let span = self.prev().span;
+        Ok(self.builder.literal(value, span))
+    }
+
+    // error production, report the operator we just consumed as missing
+    // its left operand, then parse the right operand at the operator's
+    // precedence so we can keep going. the tree is thrown away since the
+    // parse has failed, so the right operand just stands in for the whole
+    // binary expression
+    pub(super) fn missing_left_operand(&mut self) -> Result<B::Node, ParserError> {
+        let operator = self.prev().clone();
+        let precedence = Self::right_operand_precedence(operator.kind);
+        self.errors.push(ParserError::MissingLeftOperand(operator));
+        self.parse_precedence(precedence)
+    }
+}
+
+impl<I: Iterator<Item = Token>> Parser<I> {
+    pub fn new<T: IntoIterator<Item = Token, IntoIter = I>>(tokens: T) -> Parser<I> {
+        Parser::with_builder(tokens, BoxedBuilder)
     }
 }
 
 // helpers
-impl Parser {
-    pub fn new(tokens: Vec<Token>) -> Parser {
-        Parser { tokens, current: 0 }
+impl<I: Iterator<Item = Token>, B: AstBuilder> Parser<I, B> {
+    pub fn with_builder<T: IntoIterator<Item = Token, IntoIter = I>>(
+        tokens: T,
+        builder: B,
+    ) -> Parser<I, B> {
+        Parser {
+            tokens: tokens.into_iter(),
+            builder,
+            lookahead: VecDeque::with_capacity(1),
+            previous: None,
+            errors: Vec::new(),
+        }
+    }
+
+    // hand back the builder, e.g. to get at the arena the nodes went into
+    pub fn into_builder(self) -> B {
+        self.builder
     }
 
     pub fn prev(&self) -> &Token {
-        &self.tokens[self.current - 1]
+        self.previous
+            .as_ref()
+            .expect("prev() called before any token was consumed")
     }
 
     pub fn match_any(&mut self, token_types: &[TokenKind]) -> bool {
@@ -201,16 +256,33 @@ impl Parser {
 
     pub fn advance(&mut self) -> &Token {
         if !self.is_at_end() {
-            self.current += 1;
+            self.previous = self.lookahead.pop_front();
         }
         self.prev()
     }
 
-    pub fn peek(&self) -> &Token {
-        &self.tokens[self.current]
+    pub fn peek(&mut self) -> &Token {
+        if self.lookahead.is_empty() {
+            let token = self.next_token();
+            self.lookahead.push_back(token);
+        }
+        &self.lookahead[0]
+    }
+
+    // pull the next token from the input, if the input runs out without
+    // an EOF token (e.g. the reader failed) we make one up so the parser
+    // always sees the end of its input
+    fn next_token(&mut self) -> Token {
+        match self.tokens.next() {
+            Some(token) => token,
+            None => {
+                let line = self.previous.as_ref().map_or(1, |t| t.line);
+                Token::new(TokenKind::EOF, "".to_string(), line)
+            }
+        }
     }
 
-    pub fn is_at_end(&self) -> bool {
+    pub fn is_at_end(&mut self) -> bool {
         self.peek().kind == TokenKind::EOF
     }
 
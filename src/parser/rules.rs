@@ -0,0 +1,117 @@
+use super::builder::{AstBuilder, BoxedBuilder};
+use super::parser::{Parser, ParserError};
+use crate::lexer::token::{Token, TokenKind};
+
+// binding power of each operator, from loosest to tightest. the parser
+// keeps folding infix operators into the left operand for as long as
+// they bind at least as tightly as the precedence it was asked for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Precedence {
+    None,
+    Comma,       // ,
+    Conditional, // ?:
+    Equality,    // == !=
+    Comparison,  // < > <= >=
+    Term,        // + -
+    Factor,      // * /
+    Unary,       // ! -
+    Primary,
+}
+
+impl Precedence {
+    // the next tightest level, used to parse the right operand of a
+    // left associative operator
+    pub fn next(self) -> Precedence {
+        use Precedence::*;
+        match self {
+            None => Comma,
+            Comma => Conditional,
+            Conditional => Equality,
+            Equality => Comparison,
+            Comparison => Term,
+            Term => Factor,
+            Factor => Unary,
+            Unary => Primary,
+            Primary => Primary,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Associativity {
+    Left,
+    Right,
+}
+
+pub type PrefixFn<I, B> = fn(&mut Parser<I, B>) -> Result<<B as AstBuilder>::Node, ParserError>;
+pub type InfixFn<I, B> =
+    fn(&mut Parser<I, B>, <B as AstBuilder>::Node) -> Result<<B as AstBuilder>::Node, ParserError>;
+
+// how a token is parsed when it starts an expression (prefix) and when
+// it follows a complete left operand (infix)
+pub struct ParseRule<I: Iterator<Item = Token>, B: AstBuilder> {
+    pub prefix: Option<PrefixFn<I, B>>,
+    pub infix: Option<InfixFn<I, B>>,
+    pub precedence: Precedence,
+    pub associativity: Associativity,
+}
+
+impl<I: Iterator<Item = Token>, B: AstBuilder> ParseRule<I, B> {
+    fn new(
+        prefix: Option<PrefixFn<I, B>>,
+        infix: Option<InfixFn<I, B>>,
+        precedence: Precedence,
+        associativity: Associativity,
+    ) -> ParseRule<I, B> {
+        ParseRule {
+            prefix,
+            infix,
+            precedence,
+            associativity,
+        }
+    }
+}
+
+// the grammar's operator table, adding an operator to the expression
+// grammar should only need a new entry here. binary operators also get
+// `missing_left_operand` as their prefix rule, an error production for
+// expressions like `* 3` that reports the error and keeps parsing
+pub fn rule<I: Iterator<Item = Token>, B: AstBuilder>(kind: TokenKind) -> ParseRule<I, B> {
+    use Associativity::*;
+    use TokenKind::*;
+    let binary: InfixFn<I, B> = Parser::binary;
+    let missing: PrefixFn<I, B> = Parser::missing_left_operand;
+    let literal: PrefixFn<I, B> = Parser::literal;
+    match kind {
+        Comma => ParseRule::new(Some(missing), Some(binary), Precedence::Comma, Left),
+        Question => ParseRule::new(
+            None,
+            Some(Parser::conditional),
+            Precedence::Conditional,
+            Right,
+        ),
+        BangEqual | EqualEqual => {
+            ParseRule::new(Some(missing), Some(binary), Precedence::Equality, Left)
+        }
+        Greater | GreaterEqual | Less | LessEqual => {
+            ParseRule::new(Some(missing), Some(binary), Precedence::Comparison, Left)
+        }
+        Plus => ParseRule::new(Some(missing), Some(binary), Precedence::Term, Left),
+        Minus => ParseRule::new(Some(Parser::unary), Some(binary), Precedence::Term, Left),
+        Slash | Star => ParseRule::new(Some(missing), Some(binary), Precedence::Factor, Left),
+        Bang => ParseRule::new(Some(Parser::unary), None, Precedence::None, Left),
+        LeftParen => ParseRule::new(Some(Parser::grouping), None, Precedence::None, Left),
+        False | True | Nil | Number | String => {
+            ParseRule::new(Some(literal), None, Precedence::None, Left)
+        }
+        _ => ParseRule::new(None, None, Precedence::None, Left),
+    }
+}
+
+// how tightly an infix operator binds and which way it associates, for
+// code that needs to know the grammar without parsing, like printing a
+// tree back out as source
+pub fn infix_binding(kind: TokenKind) -> (Precedence, Associativity) {
+    let rule = rule::<std::iter::Empty<Token>, BoxedBuilder>(kind);
+    (rule.precedence, rule.associativity)
+}
@@ -0,0 +1,104 @@
+use super::{leftover, underflow, words, Word};
+use crate::error_handling::{LoxError, RuntimeError, RuntimeErrorKind};
+use crate::lexer::token::{Literal, Span, TokenKind};
+
+// work out the value of rpn text with a stack, without building a tree.
+// operators do what they do in lox and fail with the same runtime errors.
+// both branches of a `?:` are worked out before it picks one, so runtime
+// errors go on the stack as values and only fail the evaluation once an
+// operator other than `?:` uses them, like lox never evaluating the
+// branch it doesn't take
+pub fn evaluate(text: &str) -> Result<Literal, LoxError> {
+    let words = words(text)?;
+    let mut stack: Vec<Result<Literal, RuntimeError>> = Vec::new();
+    for (word, span) in &words {
+        let span = *span;
+        if stack.len() < word.arity() {
+            return Err(underflow(&text[span.start..span.end], word.arity(), span).into());
+        }
+        let mut operands = stack.split_off(stack.len() - word.arity()).into_iter();
+        let mut operand = || operands.next().unwrap();
+        let value = match word {
+            Word::Value(value) => Ok(value.clone()),
+            Word::Unary(kind) => operand().and_then(|right| unary(*kind, right, span)),
+            Word::Binary(kind) => {
+                let (left, right) = (operand(), operand());
+                left.and_then(|left| binary(*kind, left, right?, span))
+            }
+            Word::Conditional => {
+                let (condition, then_branch, else_branch) = (operand(), operand(), operand());
+                condition.and_then(|condition| {
+                    if condition.is_truthy() {
+                        then_branch
+                    } else {
+                        else_branch
+                    }
+                })
+            }
+        };
+        stack.push(value);
+    }
+    match stack.len() {
+        1 => Ok(stack.pop().unwrap()?),
+        count => Err(leftover(count, words.last().map(|(_, span)| *span)).into()),
+    }
+}
+
+fn unary(kind: TokenKind, right: Literal, span: Span) -> Result<Literal, RuntimeError> {
+    match (kind, right) {
+        (TokenKind::Bang, right) => Ok(Literal::Bool {
+            val: !right.is_truthy(),
+        }),
+        (_, Literal::Num { val }) => Ok(Literal::Num { val: -val }),
+        _ => Err(RuntimeError::new(
+            RuntimeErrorKind::OperandMustBeNumber,
+            span,
+        )),
+    }
+}
+
+fn binary(
+    kind: TokenKind,
+    left: Literal,
+    right: Literal,
+    span: Span,
+) -> Result<Literal, RuntimeError> {
+    use TokenKind::*;
+    let value = match (kind, left, right) {
+        (Comma, _, right) => right,
+        (EqualEqual, left, right) => Literal::Bool {
+            val: left.is_equal(&right),
+        },
+        (BangEqual, left, right) => Literal::Bool {
+            val: !left.is_equal(&right),
+        },
+        (Plus, Literal::Str { val: a, .. }, Literal::Str { val: b, .. }) => Literal::Str {
+            val: a + &b,
+            terminated: true,
+        },
+        (kind, Literal::Num { val: a }, Literal::Num { val: b }) => match kind {
+            Plus => Literal::Num { val: a + b },
+            Minus => Literal::Num { val: a - b },
+            Star => Literal::Num { val: a * b },
+            Slash => Literal::Num { val: a / b },
+            Greater => Literal::Bool { val: a > b },
+            GreaterEqual => Literal::Bool { val: a >= b },
+            Less => Literal::Bool { val: a < b },
+            LessEqual => Literal::Bool { val: a <= b },
+            _ => unreachable!("`{kind}` isn't a binary operator in rpn"),
+        },
+        (Plus, _, _) => {
+            return Err(RuntimeError::new(
+                RuntimeErrorKind::OperandsMustBeNumbersOrStrings,
+                span,
+            ))
+        }
+        _ => {
+            return Err(RuntimeError::new(
+                RuntimeErrorKind::OperandsMustBeNumbers,
+                span,
+            ))
+        }
+    };
+    Ok(value)
+}
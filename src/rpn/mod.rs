@@ -0,0 +1,270 @@
+// reverse polish notation, as written by `ReversePolishPrinter`. the text
+// is a list of words split on whitespace, each either a value or an
+// operator applied to the values before it:
+//
+//   values     numbers (`inf` and `NaN` too), "strings", true, false, nil
+//   unary      neg (minus), !
+//   binary     , == != < <= > >= + - * /
+//   ternary    ?: (condition, then branch, else branch)
+//
+// `read` rebuilds the tree the text came from, minus any groupings, and
+// `evaluate` works out its value directly on a stack
+mod eval;
+mod reader;
+
+pub use eval::evaluate;
+pub use reader::read;
+
+use crate::error_handling::RpnError;
+use crate::lexer::token::{Literal, Span, TokenKind};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Word {
+    Value(Literal),
+    Unary(TokenKind),
+    Binary(TokenKind),
+    Conditional,
+}
+
+impl Word {
+    // how many values the word takes off the stack
+    pub fn arity(&self) -> usize {
+        match self {
+            Word::Value(_) => 0,
+            Word::Unary(_) => 1,
+            Word::Binary(_) => 2,
+            Word::Conditional => 3,
+        }
+    }
+
+    fn from_text(text: &str) -> Option<Word> {
+        use TokenKind::*;
+        let word = match text {
+            "neg" => Word::Unary(Minus),
+            "!" => Word::Unary(Bang),
+            "?:" => Word::Conditional,
+            "true" => Word::Value(Literal::Bool { val: true }),
+            "false" => Word::Value(Literal::Bool { val: false }),
+            "nil" => Word::Value(Literal::Nil),
+            _ => {
+                let binary = [
+                    Comma,
+                    EqualEqual,
+                    BangEqual,
+                    Less,
+                    LessEqual,
+                    Greater,
+                    GreaterEqual,
+                    Plus,
+                    Minus,
+                    Star,
+                    Slash,
+                ];
+                match binary
+                    .into_iter()
+                    .find(|kind| kind.spelling() == Some(text))
+                {
+                    Some(kind) => Word::Binary(kind),
+                    None => Word::Value(Literal::Num {
+                        val: text.parse().ok()?,
+                    }),
+                }
+            }
+        };
+        Some(word)
+    }
+}
+
+// split the text into words, along with the span each one came from
+pub fn words(text: &str) -> Result<Vec<(Word, Span)>, RpnError> {
+    let mut words = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    let (mut line, mut column) = (1, 1);
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            if c == '\n' {
+                line += 1;
+                column = 1;
+            } else {
+                column += 1;
+            }
+            continue;
+        }
+        let (start_line, start_column) = (line, column);
+        let mut end = start;
+        let mut quoted = false;
+        // a string runs to its closing quote, spaces and newlines
+        // included, everything else runs to the next whitespace
+        while let Some(&(i, c)) = chars.peek() {
+            if c.is_whitespace() && !quoted {
+                break;
+            }
+            if c == '"' && (i == start || quoted) {
+                quoted = !quoted;
+            }
+            chars.next();
+            end = i + c.len_utf8();
+            if c == '\n' {
+                line += 1;
+                column = 1;
+            } else {
+                column += 1;
+            }
+        }
+        let span = Span::new(start, end, start_line, start_column);
+        let text = &text[start..end];
+        let word = if let Some(string) = text.strip_prefix('"') {
+            match string.strip_suffix('"') {
+                _ if quoted => return Err(RpnError::new("Unterminated string".to_string(), span)),
+                Some(val) => Word::Value(Literal::Str {
+                    val: val.to_string(),
+                    terminated: true,
+                }),
+                None => return Err(RpnError::new(format!("Unknown word `{text}`"), span)),
+            }
+        } else {
+            Word::from_text(text)
+                .ok_or_else(|| RpnError::new(format!("Unknown word `{text}`"), span))?
+        };
+        words.push((word, span));
+    }
+    Ok(words)
+}
+
+// the errors for a word without enough values before it, and for text
+// that doesn't leave exactly one value at the end
+fn underflow(word: &str, arity: usize, span: Span) -> RpnError {
+    RpnError::new(
+        format!(
+            "`{word}` needs {arity} operand{} but there aren't enough before it",
+            if arity == 1 { "" } else { "s" }
+        ),
+        span,
+    )
+}
+
+fn leftover(count: usize, last: Option<Span>) -> RpnError {
+    let span = last.unwrap_or_default();
+    match count {
+        0 => RpnError::new(
+            "Expected an expression but there are no words".to_string(),
+            span,
+        ),
+        _ => RpnError::new(
+            format!("Expected one value at the end but there are {count}, missing an operator"),
+            span,
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{evaluate, read};
+    use crate::lexer::scan_tokens;
+    use crate::lexer::token::Literal;
+    use crate::parser::parse;
+    use crate::syntax::expr::Expr;
+    use crate::syntax::printer::ReversePolishPrinter;
+
+    fn rpn(source: &str) -> String {
+        let expr = parse(scan_tokens(source)).unwrap();
+        ReversePolishPrinter {}.print(&expr)
+    }
+
+    fn error_code(text: &str) -> &'static str {
+        evaluate(text).unwrap_err().code()
+    }
+
+    #[test]
+    fn print_read_print_round_trips() {
+        let sources = [
+            "1 + 2 * 3",
+            "(1 + 2) * -3",
+            "1 - 2 - 3",
+            "1 - (2 - 3)",
+            "--1",
+            "!!nil",
+            "\"a b\" + \"c\" == \"a bc\"",
+            "true ? 1 : false ? 2 : 3",
+            "(true ? 1 : 2) ? 3 : 4",
+            "1, 2, 3",
+            "1 / 0 >= 2.5 != false",
+        ];
+        for source in sources {
+            let printed = rpn(source);
+            let reprinted = ReversePolishPrinter {}.print(&read(&printed).unwrap());
+            assert_eq!(printed, reprinted, "{source}");
+        }
+    }
+
+    #[test]
+    fn unary_minus_is_neg() {
+        assert_eq!(rpn("(1 + 2) * -3"), "1 2 + 3 neg *");
+        assert_eq!(rpn("1 - -2"), "1 2 neg -");
+    }
+
+    #[test]
+    fn read_gives_operators_their_own_span() {
+        let Expr::Unary(unary) = read("12 neg").unwrap() else {
+            panic!("expected a unary node");
+        };
+        assert_eq!((unary.operator.span.start, unary.operator.span.end), (3, 6));
+        assert_eq!((unary.span.start, unary.span.end), (0, 6));
+    }
+
+    #[test]
+    fn evaluates() {
+        let cases = [
+            ("1 2 + 3 neg *", Literal::Num { val: -9.0 }),
+            ("1 2 - 3 -", Literal::Num { val: -4.0 }),
+            (
+                "\"a\" \"b\" +",
+                Literal::Str {
+                    val: "ab".to_string(),
+                    terminated: true,
+                },
+            ),
+            ("nil !", Literal::Bool { val: true }),
+            ("1 1 == 2 3 <= ==", Literal::Bool { val: true }),
+            ("1 2 ,", Literal::Num { val: 2.0 }),
+            ("false 1 2 ?:", Literal::Num { val: 2.0 }),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(evaluate(text).unwrap(), expected, "{text}");
+        }
+    }
+
+    #[test]
+    fn only_the_taken_branch_can_fail() {
+        assert_eq!(
+            evaluate("true 1 \"a\" neg ?:").unwrap(),
+            Literal::Num { val: 1.0 }
+        );
+        assert_eq!(
+            evaluate("false \"a\" neg 1 ?:").unwrap(),
+            Literal::Num { val: 1.0 }
+        );
+        assert_eq!(error_code("true \"a\" neg 1 ?:"), "E0401");
+        // an error in the condition always fails
+        assert_eq!(error_code("\"a\" neg 1 2 ?:"), "E0401");
+        // and one in a branch that's taken fails whatever uses it
+        assert_eq!(error_code("true 1 2 + nil - 3 ?: 4 +"), "E0402");
+    }
+
+    #[test]
+    fn runtime_errors() {
+        assert_eq!(error_code("\"a\" neg"), "E0401");
+        assert_eq!(error_code("1 \"a\" <"), "E0402");
+        assert_eq!(error_code("1 \"a\" +"), "E0403");
+        assert_eq!(error_code("1 \"a\" -"), "E0402");
+    }
+
+    #[test]
+    fn malformed_text() {
+        for text in ["1 +", "1 2", "", "\"abc", "foo", "1 2 3 ?: 4", "neg"] {
+            assert_eq!(error_code(text), "E0004", "{text:?}");
+            assert_eq!(read(text).unwrap_err().code(), "E0004", "{text:?}");
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use super::{leftover, underflow, words, Word};
+use crate::error_handling::RpnError;
+use crate::lexer::token::{Token, TokenKind};
+use crate::syntax::expr::*;
+
+// rebuild the tree rpn text was printed from. rpn has no parens, so the
+// tree has no groupings, it is otherwise structurally the same as the one
+// that was printed
+pub fn read(text: &str) -> Result<Expr, RpnError> {
+    let words = words(text)?;
+    let mut stack: Vec<Expr> = Vec::new();
+    for (word, span) in &words {
+        let span = *span;
+        if stack.len() < word.arity() {
+            return Err(underflow(&text[span.start..span.end], word.arity(), span));
+        }
+        let mut operands = stack.split_off(stack.len() - word.arity()).into_iter();
+        let mut operand = || Box::new(operands.next().unwrap());
+        let node = match word {
+            Word::Value(value) => Expr::Literal(LiteralExpr::new(value.clone(), span)),
+            Word::Unary(kind) => {
+                let right = operand();
+                let node_span = right.span().to(span);
+                Expr::Unary(UnaryExpr::new(operator(*kind, span), right, node_span))
+            }
+            Word::Binary(kind) => {
+                let (left, right) = (operand(), operand());
+                let node_span = left.span().to(span);
+                Expr::Binary(BinaryExpr::new(
+                    left,
+                    operator(*kind, span),
+                    right,
+                    node_span,
+                ))
+            }
+            Word::Conditional => {
+                let (condition, then_branch, else_branch) = (operand(), operand(), operand());
+                let span = condition.span().to(span);
+                Expr::Conditional(ConditionalExpr::new(
+                    condition,
+                    then_branch,
+                    else_branch,
+                    span,
+                ))
+            }
+        };
+        stack.push(node);
+    }
+    match stack.len() {
+        1 => Ok(stack.pop().unwrap()),
+        count => Err(leftover(count, words.last().map(|(_, span)| *span))),
+    }
+}
+
+// the token the parser would have made for the operator
+fn operator(kind: TokenKind, span: crate::lexer::token::Span) -> Token {
+    Token::new(
+        kind,
+        kind.spelling().unwrap_or_default().to_string(),
+        span.line,
+    )
+    .span(span)
+}
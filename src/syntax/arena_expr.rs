@@ -0,0 +1,229 @@
+// generated by define_ast from ast.schema, do not edit by hand
+
+pub use crate::lexer::token::{Literal, Span, Token};
+pub use crate::syntax::NodeId;
+
+// handle to a node stored in an ExprArena
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ExprRef(u32);
+
+impl ExprRef {
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ExprArena {
+    nodes: Vec<Expr>,
+}
+
+impl ExprArena {
+    pub fn new() -> ExprArena {
+        ExprArena::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> ExprArena {
+        ExprArena {
+            nodes: Vec::with_capacity(capacity),
+        }
+    }
+
+    pub fn alloc(&mut self, node: Expr) -> ExprRef {
+        self.nodes.push(node);
+        ExprRef((self.nodes.len() - 1) as u32)
+    }
+
+    pub fn get(&self, node: ExprRef) -> &Expr {
+        &self.nodes[node.index()]
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn walk_expr<T>(&self, node: ExprRef, v: &dyn ExprVisitor<T>) -> T {
+        self.get(node).walk_expr(self, v)
+    }
+}
+
+impl std::ops::Index<ExprRef> for ExprArena {
+    type Output = Expr;
+
+    fn index(&self, node: ExprRef) -> &Expr {
+        self.get(node)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Binary(BinaryExpr),
+    Conditional(ConditionalExpr),
+    Grouping(GroupingExpr),
+    Literal(LiteralExpr),
+    Unary(UnaryExpr),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BinaryExpr {
+    pub id: NodeId,
+    pub left: ExprRef,
+    pub operator: Token,
+    pub right: ExprRef,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConditionalExpr {
+    pub id: NodeId,
+    pub condition: ExprRef,
+    pub then_branch: ExprRef,
+    pub else_branch: ExprRef,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GroupingExpr {
+    pub id: NodeId,
+    pub expression: ExprRef,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LiteralExpr {
+    pub id: NodeId,
+    pub value: Literal,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnaryExpr {
+    pub id: NodeId,
+    pub operator: Token,
+    pub right: ExprRef,
+    pub span: Span,
+}
+
+pub trait ExprVisitor<T> {
+    fn visit_binary_expr(&self, arena: &ExprArena, e: &BinaryExpr) -> T;
+    fn visit_conditional_expr(&self, arena: &ExprArena, e: &ConditionalExpr) -> T;
+    fn visit_grouping_expr(&self, arena: &ExprArena, e: &GroupingExpr) -> T;
+    fn visit_literal_expr(&self, arena: &ExprArena, e: &LiteralExpr) -> T;
+    fn visit_unary_expr(&self, arena: &ExprArena, e: &UnaryExpr) -> T;
+}
+
+impl Expr {
+    pub fn walk_expr<T>(&self, arena: &ExprArena, v: &dyn ExprVisitor<T>) -> T {
+        match self {
+            Expr::Binary(e) => e.walk_binary_expr(arena, v),
+            Expr::Conditional(e) => e.walk_conditional_expr(arena, v),
+            Expr::Grouping(e) => e.walk_grouping_expr(arena, v),
+            Expr::Literal(e) => e.walk_literal_expr(arena, v),
+            Expr::Unary(e) => e.walk_unary_expr(arena, v),
+        }
+    }
+
+    pub fn id(&self) -> NodeId {
+        match self {
+            Expr::Binary(e) => e.id,
+            Expr::Conditional(e) => e.id,
+            Expr::Grouping(e) => e.id,
+            Expr::Literal(e) => e.id,
+            Expr::Unary(e) => e.id,
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            Expr::Binary(e) => e.span,
+            Expr::Conditional(e) => e.span,
+            Expr::Grouping(e) => e.span,
+            Expr::Literal(e) => e.span,
+            Expr::Unary(e) => e.span,
+        }
+    }
+}
+
+impl BinaryExpr {
+    pub fn new(left: ExprRef, operator: Token, right: ExprRef, span: Span) -> BinaryExpr {
+        BinaryExpr {
+            id: NodeId::fresh(),
+            left,
+            operator,
+            right,
+            span,
+        }
+    }
+
+    pub fn walk_binary_expr<T>(&self, arena: &ExprArena, v: &dyn ExprVisitor<T>) -> T {
+        v.visit_binary_expr(arena, self)
+    }
+}
+
+impl ConditionalExpr {
+    pub fn new(
+        condition: ExprRef,
+        then_branch: ExprRef,
+        else_branch: ExprRef,
+        span: Span,
+    ) -> ConditionalExpr {
+        ConditionalExpr {
+            id: NodeId::fresh(),
+            condition,
+            then_branch,
+            else_branch,
+            span,
+        }
+    }
+
+    pub fn walk_conditional_expr<T>(&self, arena: &ExprArena, v: &dyn ExprVisitor<T>) -> T {
+        v.visit_conditional_expr(arena, self)
+    }
+}
+
+impl GroupingExpr {
+    pub fn new(expression: ExprRef, span: Span) -> GroupingExpr {
+        GroupingExpr {
+            id: NodeId::fresh(),
+            expression,
+            span,
+        }
+    }
+
+    pub fn walk_grouping_expr<T>(&self, arena: &ExprArena, v: &dyn ExprVisitor<T>) -> T {
+        v.visit_grouping_expr(arena, self)
+    }
+}
+
+impl LiteralExpr {
+    pub fn new(value: Literal, span: Span) -> LiteralExpr {
+        LiteralExpr {
+            id: NodeId::fresh(),
+            value,
+            span,
+        }
+    }
+
+    pub fn walk_literal_expr<T>(&self, arena: &ExprArena, v: &dyn ExprVisitor<T>) -> T {
+        v.visit_literal_expr(arena, self)
+    }
+}
+
+impl UnaryExpr {
+    pub fn new(operator: Token, right: ExprRef, span: Span) -> UnaryExpr {
+        UnaryExpr {
+            id: NodeId::fresh(),
+            operator,
+            right,
+            span,
+        }
+    }
+
+    pub fn walk_unary_expr<T>(&self, arena: &ExprArena, v: &dyn ExprVisitor<T>) -> T {
+        v.visit_unary_expr(arena, self)
+    }
+}
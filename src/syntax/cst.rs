@@ -0,0 +1,178 @@
+use crate::lexer::token::{Literal, Span, Token, TokenKind};
+use crate::syntax::expr::*;
+
+// a concrete syntax tree keeps every token of the source, parens and
+// comments included, along with the whitespace between them, so printing
+// it gives back the exact source it was parsed from. it's for tools that
+// edit source, like the formatter, rather than ones that only care what
+// it means. build one with `parser::parse_cst` and `lower` it to get the
+// `Expr` the parser would have given
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyntaxKind {
+    // the whole source, the expression and then the end of input token
+    Root,
+    Binary,
+    Conditional,
+    Grouping,
+    Literal,
+    Unary,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriviaKind {
+    Whitespace,
+    Comment,
+    BlockComment,
+}
+
+// source between tokens that doesn't change what the program means
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+    pub text: String,
+}
+
+// a token along with the trivia before it. the trivia at the end of the
+// source belongs to the end of input token
+#[derive(Debug, Clone, PartialEq)]
+pub struct CstToken {
+    pub leading: Vec<Trivia>,
+    pub token: Token,
+    // exactly what the token was in the source
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CstElement {
+    Node(CstNode),
+    Token(CstToken),
+}
+
+// a node and everything in it, in source order
+#[derive(Debug, Clone, PartialEq)]
+pub struct CstNode {
+    pub kind: SyntaxKind,
+    pub span: Span,
+    pub children: Vec<CstElement>,
+}
+
+impl CstNode {
+    pub fn nodes(&self) -> impl Iterator<Item = &CstNode> {
+        self.children.iter().filter_map(|child| match child {
+            CstElement::Node(node) => Some(node),
+            CstElement::Token(_) => None,
+        })
+    }
+
+    pub fn tokens(&self) -> impl Iterator<Item = &CstToken> {
+        self.children.iter().filter_map(|child| match child {
+            CstElement::Token(token) => Some(token),
+            CstElement::Node(_) => None,
+        })
+    }
+
+    // the abstract tree for this node, the same one `parser::parse` gives
+    // for the same source, spans included. the node has to be shaped like
+    // the ones the parser builds
+    pub fn lower(&self) -> Expr {
+        let mut nodes = self.nodes().map(|node| Box::new(node.lower()));
+        let mut node = || nodes.next().expect("node is missing a child");
+        // the only token a binary or unary node has is its operator
+        let operator = || {
+            self.tokens()
+                .next()
+                .expect("node is missing its operator")
+                .token
+                .clone()
+        };
+        match self.kind {
+            // the only other thing in the root is the end of input token
+            SyntaxKind::Root => *node(),
+            SyntaxKind::Binary => {
+                let left = node();
+                Expr::Binary(BinaryExpr::new(left, operator(), node(), self.span))
+            }
+            SyntaxKind::Conditional => {
+                let (condition, then_branch) = (node(), node());
+                Expr::Conditional(ConditionalExpr::new(
+                    condition,
+                    then_branch,
+                    node(),
+                    self.span,
+                ))
+            }
+            SyntaxKind::Grouping => Expr::Grouping(GroupingExpr::new(node(), self.span)),
+            SyntaxKind::Literal => {
+                let token = &self
+                    .tokens()
+                    .next()
+                    .expect("literal is missing its token")
+                    .token;
+                let value = match token.kind {
+                    TokenKind::False => Literal::Bool { val: false },
+                    TokenKind::True => Literal::Bool { val: true },
+                    TokenKind::Nil => Literal::Nil,
+                    _ => token.literal.clone().expect("literal token has no value"),
+                };
+                Expr::Literal(LiteralExpr::new(value, self.span))
+            }
+            SyntaxKind::Unary => Expr::Unary(UnaryExpr::new(operator(), node(), self.span)),
+        }
+    }
+}
+
+impl CstNode {
+    // an indented outline of the tree, one node, token or piece of trivia
+    // per line, for seeing what the parser made of some source
+    pub fn outline(&self) -> String {
+        let mut out = String::new();
+        self.write_outline(&mut out, 0);
+        out
+    }
+
+    fn write_outline(&self, out: &mut String, depth: usize) {
+        let indent = "  ".repeat(depth);
+        out.push_str(&format!(
+            "{indent}{:?}@{}..{}\n",
+            self.kind, self.span.start, self.span.end
+        ));
+        for child in &self.children {
+            match child {
+                CstElement::Node(node) => node.write_outline(out, depth + 1),
+                CstElement::Token(token) => {
+                    for trivia in &token.leading {
+                        out.push_str(&format!("{indent}  {:?} {:?}\n", trivia.kind, trivia.text));
+                    }
+                    let span = token.token.span;
+                    out.push_str(&format!(
+                        "{indent}  {}@{}..{} {:?}\n",
+                        token.token.kind, span.start, span.end, token.text
+                    ));
+                }
+            }
+        }
+    }
+}
+
+// prints the source the tree was parsed from, byte for byte
+impl std::fmt::Display for CstNode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for child in &self.children {
+            match child {
+                CstElement::Node(node) => write!(f, "{node}")?,
+                CstElement::Token(token) => write!(f, "{token}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for CstToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for trivia in &self.leading {
+            write!(f, "{}", trivia.text)?;
+        }
+        write!(f, "{}", self.text)
+    }
+}
@@ -1,163 +1,397 @@
-pub use crate::lexer::token::{Literal, Token};
+// generated by define_ast from ast.schema, do not edit by hand
 
+use std::hash::{Hash, Hasher};
+
+pub use crate::lexer::token::{Literal, Span, Token};
+pub use crate::syntax::NodeId;
+use crate::syntax::Structural;
+
+// `==` means the same nodes. ids are compared too, so two parses of the
+// same source are never `==`, compare those with `structural_eq`. a clone
+// keeps the ids of the original, it is a copy of the same nodes and side
+// tables keyed by id apply to both
+#[derive(Debug, Clone, PartialEq)]
 pub enum Expr {
-	Binary(BinaryExpr),
-	Grouping(GroupingExpr),
-	Literal(LiteralExpr),
-	Unary(UnaryExpr),
+    Binary(BinaryExpr),
+    Conditional(ConditionalExpr),
+    Grouping(GroupingExpr),
+    Literal(LiteralExpr),
+    Unary(UnaryExpr),
 }
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct BinaryExpr {
-	pub left: Box<Expr>,
-	pub operator: Token,
-	pub right: Box<Expr>,
+    pub id: NodeId,
+    pub left: Box<Expr>,
+    pub operator: Token,
+    pub right: Box<Expr>,
+    pub span: Span,
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConditionalExpr {
+    pub id: NodeId,
+    pub condition: Box<Expr>,
+    pub then_branch: Box<Expr>,
+    pub else_branch: Box<Expr>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct GroupingExpr {
-	pub expression: Box<Expr>,
+    pub id: NodeId,
+    pub expression: Box<Expr>,
+    pub span: Span,
 }
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct LiteralExpr {
-	pub value: Literal,
+    pub id: NodeId,
+    pub value: Literal,
+    pub span: Span,
 }
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct UnaryExpr {
-	pub operator: Token,
-	pub right: Box<Expr>,
+    pub id: NodeId,
+    pub operator: Token,
+    pub right: Box<Expr>,
+    pub span: Span,
 }
 
 pub trait ExprVisitor<T> {
-	fn visit_binary_expr(&self, e: &BinaryExpr) -> T;
-	fn visit_grouping_expr(&self, e: &GroupingExpr) -> T;
-	fn visit_literal_expr(&self, e: &LiteralExpr) -> T;
-	fn visit_unary_expr(&self, e: &UnaryExpr) -> T;
+    fn visit_binary_expr(&self, e: &BinaryExpr) -> T;
+    fn visit_conditional_expr(&self, e: &ConditionalExpr) -> T;
+    fn visit_grouping_expr(&self, e: &GroupingExpr) -> T;
+    fn visit_literal_expr(&self, e: &LiteralExpr) -> T;
+    fn visit_unary_expr(&self, e: &UnaryExpr) -> T;
 }
 
 impl Expr {
-	pub fn walk_expr<T>(&self, v: &dyn ExprVisitor<T>) -> T {
-		match self {
-			Expr::Binary(e) => e.walk_binary_expr(v),
-			Expr::Grouping(e) => e.walk_grouping_expr(v),
-			Expr::Literal(e) => e.walk_literal_expr(v),
-			Expr::Unary(e) => e.walk_unary_expr(v),
-		}
-	}
+    pub fn walk_expr<T>(&self, v: &dyn ExprVisitor<T>) -> T {
+        match self {
+            Expr::Binary(e) => e.walk_binary_expr(v),
+            Expr::Conditional(e) => e.walk_conditional_expr(v),
+            Expr::Grouping(e) => e.walk_grouping_expr(v),
+            Expr::Literal(e) => e.walk_literal_expr(v),
+            Expr::Unary(e) => e.walk_unary_expr(v),
+        }
+    }
+
+    pub fn id(&self) -> NodeId {
+        match self {
+            Expr::Binary(e) => e.id,
+            Expr::Conditional(e) => e.id,
+            Expr::Grouping(e) => e.id,
+            Expr::Literal(e) => e.id,
+            Expr::Unary(e) => e.id,
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            Expr::Binary(e) => e.span,
+            Expr::Conditional(e) => e.span,
+            Expr::Grouping(e) => e.span,
+            Expr::Literal(e) => e.span,
+            Expr::Unary(e) => e.span,
+        }
+    }
 }
 
 impl BinaryExpr {
-	pub fn new(left: Box<Expr>, operator: Token, right: Box<Expr>) -> BinaryExpr {
-		BinaryExpr {
-			left,
-			operator,
-			right,
-		}
-	}
+    pub fn new(left: Box<Expr>, operator: Token, right: Box<Expr>, span: Span) -> BinaryExpr {
+        BinaryExpr {
+            id: NodeId::fresh(),
+            left,
+            operator,
+            right,
+            span,
+        }
+    }
+
+    pub fn walk_binary_expr<T>(&self, v: &dyn ExprVisitor<T>) -> T {
+        v.visit_binary_expr(self)
+    }
+}
+
+impl ConditionalExpr {
+    pub fn new(
+        condition: Box<Expr>,
+        then_branch: Box<Expr>,
+        else_branch: Box<Expr>,
+        span: Span,
+    ) -> ConditionalExpr {
+        ConditionalExpr {
+            id: NodeId::fresh(),
+            condition,
+            then_branch,
+            else_branch,
+            span,
+        }
+    }
 
-	pub fn walk_binary_expr<T>(&self, v: &dyn ExprVisitor<T>) -> T {
-		v.visit_binary_expr(self)
-	}
+    pub fn walk_conditional_expr<T>(&self, v: &dyn ExprVisitor<T>) -> T {
+        v.visit_conditional_expr(self)
+    }
 }
 
 impl GroupingExpr {
-	pub fn new(expression: Box<Expr>) -> GroupingExpr {
-		GroupingExpr { expression }
-	}
+    pub fn new(expression: Box<Expr>, span: Span) -> GroupingExpr {
+        GroupingExpr {
+            id: NodeId::fresh(),
+            expression,
+            span,
+        }
+    }
 
-	pub fn walk_grouping_expr<T>(&self, v: &dyn ExprVisitor<T>) -> T {
-		v.visit_grouping_expr(self)
-	}
+    pub fn walk_grouping_expr<T>(&self, v: &dyn ExprVisitor<T>) -> T {
+        v.visit_grouping_expr(self)
+    }
 }
 
 impl LiteralExpr {
-	pub fn new(value: Literal) -> LiteralExpr {
-		LiteralExpr { value }
-	}
+    pub fn new(value: Literal, span: Span) -> LiteralExpr {
+        LiteralExpr {
+            id: NodeId::fresh(),
+            value,
+            span,
+        }
+    }
 
-	pub fn walk_literal_expr<T>(&self, v: &dyn ExprVisitor<T>) -> T {
-		v.visit_literal_expr(self)
-	}
+    pub fn walk_literal_expr<T>(&self, v: &dyn ExprVisitor<T>) -> T {
+        v.visit_literal_expr(self)
+    }
 }
 
 impl UnaryExpr {
-	pub fn new(operator: Token, right: Box<Expr>) -> UnaryExpr {
-		UnaryExpr { operator, right }
-	}
-
-	pub fn walk_unary_expr<T>(&self, v: &dyn ExprVisitor<T>) -> T {
-		v.visit_unary_expr(self)
-	}
-}
-
-pub struct AstPrinter;
-impl AstPrinter {
-	pub fn print(&self, e: &Expr) -> String {
-		e.walk_expr(self)
-	}
-}
-impl ExprVisitor<String> for AstPrinter {
-	fn visit_binary_expr(&self, b: &BinaryExpr) -> String {
-		self.parenthesize(&b.operator.lexeme, &[&b.left, &b.right])
-	}
-	fn visit_grouping_expr(&self, g: &GroupingExpr) -> String {
-		self.parenthesize(&"group".to_string(), &[&g.expression])
-	}
-	fn visit_literal_expr(&self, l: &LiteralExpr) -> String {
-		match &l.value {
-			Literal::Num { val } => val.to_string(),
-			Literal::Str { val, .. } => val.clone(),
-		}
-	}
-	fn visit_unary_expr(&self, u: &UnaryExpr) -> String {
-		self.parenthesize(&u.operator.lexeme, &[&u.right])
-	}
-}
-
-impl AstPrinter {
-	pub fn parenthesize(&self, name: &String, exprs: &[&Box<Expr>]) -> String {
-		let mut ret: String = String::from(format!("({name}"));
-		for x in exprs {
-			let some = format!(" {}", x.walk_expr(self));
-			ret.push_str(some.as_str());
-		}
-		ret.push(')');
-		ret
-	}
-}
-
-pub struct ReversePolishPrinter {}
-impl ReversePolishPrinter {
-	pub fn print(&self, e: &Expr) -> String {
-		e.walk_expr(self)
-	}
-
-	pub fn infix_to_polish(&self, binary_expr: &BinaryExpr) -> String {
-		format!(
-			"{} {} {}",
-			binary_expr.left.walk_expr(self),
-			binary_expr.right.walk_expr(self),
-			binary_expr.operator.lexeme
-		)
-	}
-}
-
-impl ExprVisitor<String> for ReversePolishPrinter {
-	fn visit_binary_expr(&self, b: &BinaryExpr) -> String {
-		self.infix_to_polish(b)
-	}
-	fn visit_grouping_expr(&self, g: &GroupingExpr) -> String {
-		g.expression.walk_expr(self)
-	}
-	fn visit_literal_expr(&self, l: &LiteralExpr) -> String {
-		match &l.value {
-			Literal::Num { val } => val.to_string(),
-			Literal::Str { val, .. } => val.clone(),
-		}
-	}
-	fn visit_unary_expr(&self, u: &UnaryExpr) -> String {
-		// unary expr would not be valid in RPN if it is the same character
-		// as binary operator (i.e. '-' cannot be used for "-2" and "2 - 1")
-		// therefore this unary expr is just to satisfy the ExprVisitor trait
-		// and doesn't conform with our standard math exprs
-		format!("{} {}", u.right.walk_expr(self), u.operator.lexeme)
-	}
+    pub fn new(operator: Token, right: Box<Expr>, span: Span) -> UnaryExpr {
+        UnaryExpr {
+            id: NodeId::fresh(),
+            operator,
+            right,
+            span,
+        }
+    }
+
+    pub fn walk_unary_expr<T>(&self, v: &dyn ExprVisitor<T>) -> T {
+        v.visit_unary_expr(self)
+    }
+}
+
+pub trait ExprVisitorMut {
+    fn visit_expr(&mut self, e: &Expr) {
+        walk_expr_mut(self, e)
+    }
+
+    fn visit_binary_expr(&mut self, e: &BinaryExpr) {
+        walk_binary_expr_mut(self, e)
+    }
+
+    fn visit_conditional_expr(&mut self, e: &ConditionalExpr) {
+        walk_conditional_expr_mut(self, e)
+    }
+
+    fn visit_grouping_expr(&mut self, e: &GroupingExpr) {
+        walk_grouping_expr_mut(self, e)
+    }
+
+    fn visit_literal_expr(&mut self, e: &LiteralExpr) {
+        walk_literal_expr_mut(self, e)
+    }
+
+    fn visit_unary_expr(&mut self, e: &UnaryExpr) {
+        walk_unary_expr_mut(self, e)
+    }
+}
+
+pub fn walk_expr_mut<V: ExprVisitorMut + ?Sized>(v: &mut V, e: &Expr) {
+    match e {
+        Expr::Binary(e) => v.visit_binary_expr(e),
+        Expr::Conditional(e) => v.visit_conditional_expr(e),
+        Expr::Grouping(e) => v.visit_grouping_expr(e),
+        Expr::Literal(e) => v.visit_literal_expr(e),
+        Expr::Unary(e) => v.visit_unary_expr(e),
+    }
+}
+
+pub fn walk_binary_expr_mut<V: ExprVisitorMut + ?Sized>(v: &mut V, e: &BinaryExpr) {
+    v.visit_expr(&e.left);
+    v.visit_expr(&e.right);
+}
+
+pub fn walk_conditional_expr_mut<V: ExprVisitorMut + ?Sized>(v: &mut V, e: &ConditionalExpr) {
+    v.visit_expr(&e.condition);
+    v.visit_expr(&e.then_branch);
+    v.visit_expr(&e.else_branch);
+}
+
+pub fn walk_grouping_expr_mut<V: ExprVisitorMut + ?Sized>(v: &mut V, e: &GroupingExpr) {
+    v.visit_expr(&e.expression);
+}
+
+pub fn walk_literal_expr_mut<V: ExprVisitorMut + ?Sized>(_: &mut V, _: &LiteralExpr) {}
+
+pub fn walk_unary_expr_mut<V: ExprVisitorMut + ?Sized>(v: &mut V, e: &UnaryExpr) {
+    v.visit_expr(&e.right);
+}
+
+pub trait ExprFold {
+    fn fold_expr(&mut self, e: Expr) -> Expr {
+        fold_expr(self, e)
+    }
+
+    fn fold_binary_expr(&mut self, e: BinaryExpr) -> Expr {
+        fold_binary_expr(self, e)
+    }
+
+    fn fold_conditional_expr(&mut self, e: ConditionalExpr) -> Expr {
+        fold_conditional_expr(self, e)
+    }
+
+    fn fold_grouping_expr(&mut self, e: GroupingExpr) -> Expr {
+        fold_grouping_expr(self, e)
+    }
+
+    fn fold_literal_expr(&mut self, e: LiteralExpr) -> Expr {
+        fold_literal_expr(self, e)
+    }
+
+    fn fold_unary_expr(&mut self, e: UnaryExpr) -> Expr {
+        fold_unary_expr(self, e)
+    }
+}
+
+pub fn fold_expr<F: ExprFold + ?Sized>(f: &mut F, e: Expr) -> Expr {
+    match e {
+        Expr::Binary(e) => f.fold_binary_expr(e),
+        Expr::Conditional(e) => f.fold_conditional_expr(e),
+        Expr::Grouping(e) => f.fold_grouping_expr(e),
+        Expr::Literal(e) => f.fold_literal_expr(e),
+        Expr::Unary(e) => f.fold_unary_expr(e),
+    }
+}
+
+pub fn fold_binary_expr<F: ExprFold + ?Sized>(f: &mut F, e: BinaryExpr) -> Expr {
+    Expr::Binary(BinaryExpr {
+        id: e.id,
+        left: Box::new(f.fold_expr(*e.left)),
+        operator: e.operator,
+        right: Box::new(f.fold_expr(*e.right)),
+        span: e.span,
+    })
+}
+
+pub fn fold_conditional_expr<F: ExprFold + ?Sized>(f: &mut F, e: ConditionalExpr) -> Expr {
+    Expr::Conditional(ConditionalExpr {
+        id: e.id,
+        condition: Box::new(f.fold_expr(*e.condition)),
+        then_branch: Box::new(f.fold_expr(*e.then_branch)),
+        else_branch: Box::new(f.fold_expr(*e.else_branch)),
+        span: e.span,
+    })
+}
+
+pub fn fold_grouping_expr<F: ExprFold + ?Sized>(f: &mut F, e: GroupingExpr) -> Expr {
+    Expr::Grouping(GroupingExpr {
+        id: e.id,
+        expression: Box::new(f.fold_expr(*e.expression)),
+        span: e.span,
+    })
+}
+
+pub fn fold_literal_expr<F: ExprFold + ?Sized>(_: &mut F, e: LiteralExpr) -> Expr {
+    Expr::Literal(e)
+}
+
+pub fn fold_unary_expr<F: ExprFold + ?Sized>(f: &mut F, e: UnaryExpr) -> Expr {
+    Expr::Unary(UnaryExpr {
+        id: e.id,
+        operator: e.operator,
+        right: Box::new(f.fold_expr(*e.right)),
+        span: e.span,
+    })
+}
+
+impl Structural for Expr {
+    fn structural_eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Expr::Binary(a), Expr::Binary(b)) => a.structural_eq(b),
+            (Expr::Conditional(a), Expr::Conditional(b)) => a.structural_eq(b),
+            (Expr::Grouping(a), Expr::Grouping(b)) => a.structural_eq(b),
+            (Expr::Literal(a), Expr::Literal(b)) => a.structural_eq(b),
+            (Expr::Unary(a), Expr::Unary(b)) => a.structural_eq(b),
+            _ => false,
+        }
+    }
+
+    fn structural_hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            Expr::Binary(e) => e.structural_hash(state),
+            Expr::Conditional(e) => e.structural_hash(state),
+            Expr::Grouping(e) => e.structural_hash(state),
+            Expr::Literal(e) => e.structural_hash(state),
+            Expr::Unary(e) => e.structural_hash(state),
+        }
+    }
+}
+
+impl Structural for BinaryExpr {
+    fn structural_eq(&self, other: &Self) -> bool {
+        self.left.structural_eq(&other.left)
+            && self.operator.structural_eq(&other.operator)
+            && self.right.structural_eq(&other.right)
+    }
+
+    fn structural_hash<H: Hasher>(&self, state: &mut H) {
+        self.left.structural_hash(state);
+        self.operator.structural_hash(state);
+        self.right.structural_hash(state);
+    }
+}
+
+impl Structural for ConditionalExpr {
+    fn structural_eq(&self, other: &Self) -> bool {
+        self.condition.structural_eq(&other.condition)
+            && self.then_branch.structural_eq(&other.then_branch)
+            && self.else_branch.structural_eq(&other.else_branch)
+    }
+
+    fn structural_hash<H: Hasher>(&self, state: &mut H) {
+        self.condition.structural_hash(state);
+        self.then_branch.structural_hash(state);
+        self.else_branch.structural_hash(state);
+    }
+}
+
+impl Structural for GroupingExpr {
+    fn structural_eq(&self, other: &Self) -> bool {
+        self.expression.structural_eq(&other.expression)
+    }
+
+    fn structural_hash<H: Hasher>(&self, state: &mut H) {
+        self.expression.structural_hash(state);
+    }
+}
+
+impl Structural for LiteralExpr {
+    fn structural_eq(&self, other: &Self) -> bool {
+        self.value.structural_eq(&other.value)
+    }
+
+    fn structural_hash<H: Hasher>(&self, state: &mut H) {
+        self.value.structural_hash(state);
+    }
+}
+
+impl Structural for UnaryExpr {
+    fn structural_eq(&self, other: &Self) -> bool {
+        self.operator.structural_eq(&other.operator) && self.right.structural_eq(&other.right)
+    }
+
+    fn structural_hash<H: Hasher>(&self, state: &mut H) {
+        self.operator.structural_hash(state);
+        self.right.structural_hash(state);
+    }
 }
@@ -0,0 +1,488 @@
+// the syntax tree as json, for tools outside the interpreter that want to
+// read trees or hand us trees to run. the format is part of our output
+// like `tokens --json` and diagnostics, so only ever add fields to it.
+//
+// every node is an object with a `kind` naming the node, then its fields
+// in the order the schema declares them, then the span of source it
+// covers:
+//
+//   {"kind":"Binary","left":<expr>,"operator":<token>,"right":<expr>,"span":<span>}
+//   {"kind":"Conditional","condition":<expr>,"then_branch":<expr>,"else_branch":<expr>,"span":<span>}
+//   {"kind":"Grouping","expression":<expr>,"span":<span>}
+//   {"kind":"Literal","value":<literal>,"span":<span>}
+//   {"kind":"Unary","operator":<token>,"right":<expr>,"span":<span>}
+//
+// spans are byte offsets along with the line and column they start on:
+//
+//   {"start":2,"end":3,"line":1,"column":3}
+//
+// operators are tokens, with the `TokenKind` spelled as in `tokens`, its
+// lexeme and span:
+//
+//   {"kind":"Plus","lexeme":"+","span":<span>}
+//
+// literals say what type of value they hold:
+//
+//   {"type":"number","value":1.5}
+//   {"type":"string","value":"hi"}
+//   {"type":"bool","value":true}
+//   {"type":"nil"}
+//
+// json has no nan or infinities, so those numbers are written as the
+// strings "NaN", "inf" and "-inf". node ids are only unique within a run,
+// so they aren't written and nodes that are read get fresh ones. when
+// reading, spans and an operator's lexeme can be left out, and get an
+// empty span and the operator's usual spelling. operators have to be ones
+// the parser could have put there, so any tree we read back can be
+// printed and run like a parsed one
+use crate::error_handling::JsonError;
+use crate::json::{self, Value};
+use crate::lexer::token::{Literal, Span, Token, TokenKind};
+use crate::parser::{infix_binding, Precedence};
+use crate::syntax::expr::*;
+
+pub fn to_json(e: &Expr) -> String {
+    e.walk_expr(&JsonPrinter)
+}
+
+// read a tree from a json document in the format above
+pub fn from_json(source: &str) -> Result<Expr, JsonError> {
+    expr(&json::parse(source)?, "$", 0)
+}
+
+// read a tree from already parsed json
+pub fn from_value(value: &Value) -> Result<Expr, JsonError> {
+    expr(value, "$", 0)
+}
+
+struct JsonPrinter;
+
+impl ExprVisitor<String> for JsonPrinter {
+    fn visit_binary_expr(&self, b: &BinaryExpr) -> String {
+        format!(
+            "{{\"kind\":\"Binary\",\"left\":{},\"operator\":{},\"right\":{},\"span\":{}}}",
+            b.left.walk_expr(self),
+            token_to_json(&b.operator),
+            b.right.walk_expr(self),
+            b.span.to_json()
+        )
+    }
+    fn visit_conditional_expr(&self, c: &ConditionalExpr) -> String {
+        format!(
+            "{{\"kind\":\"Conditional\",\"condition\":{},\"then_branch\":{},\"else_branch\":{},\"span\":{}}}",
+            c.condition.walk_expr(self),
+            c.then_branch.walk_expr(self),
+            c.else_branch.walk_expr(self),
+            c.span.to_json()
+        )
+    }
+    fn visit_grouping_expr(&self, g: &GroupingExpr) -> String {
+        format!(
+            "{{\"kind\":\"Grouping\",\"expression\":{},\"span\":{}}}",
+            g.expression.walk_expr(self),
+            g.span.to_json()
+        )
+    }
+    fn visit_literal_expr(&self, l: &LiteralExpr) -> String {
+        let value = match &l.value {
+            Literal::Num { val } if val.is_nan() => {
+                "{\"type\":\"number\",\"value\":\"NaN\"}".to_string()
+            }
+            Literal::Num { val } if val.is_infinite() => format!(
+                "{{\"type\":\"number\",\"value\":\"{}inf\"}}",
+                if *val < 0.0 { "-" } else { "" }
+            ),
+            Literal::Num { val } => {
+                format!("{{\"type\":\"number\",\"value\":{}}}", json::number(*val))
+            }
+            Literal::Str { val, .. } => {
+                format!("{{\"type\":\"string\",\"value\":{}}}", json::quote(val))
+            }
+            Literal::Bool { val } => format!("{{\"type\":\"bool\",\"value\":{val}}}"),
+            Literal::Nil => "{\"type\":\"nil\"}".to_string(),
+        };
+        format!(
+            "{{\"kind\":\"Literal\",\"value\":{value},\"span\":{}}}",
+            l.span.to_json()
+        )
+    }
+    fn visit_unary_expr(&self, u: &UnaryExpr) -> String {
+        format!(
+            "{{\"kind\":\"Unary\",\"operator\":{},\"right\":{},\"span\":{}}}",
+            token_to_json(&u.operator),
+            u.right.walk_expr(self),
+            u.span.to_json()
+        )
+    }
+}
+
+fn token_to_json(token: &Token) -> String {
+    format!(
+        "{{\"kind\":{},\"lexeme\":{},\"span\":{}}}",
+        json::quote(&token.kind.to_string()),
+        json::quote(&token.lexeme),
+        token.span.to_json()
+    )
+}
+
+// shape errors are found after parsing, so they point at the offending
+// value with a path like `$.left.operator` instead of a line
+fn error(path: &str, message: String) -> JsonError {
+    JsonError::new(0, format!("{message} at `{path}`"))
+}
+
+fn field<'a>(value: &'a Value, path: &str, name: &str) -> Result<&'a Value, JsonError> {
+    match value {
+        Value::Object(_) => value
+            .get(name)
+            .ok_or_else(|| error(path, format!("Missing field `{name}`"))),
+        _ => Err(error(
+            path,
+            format!("Expected an object but found {}", value.type_name()),
+        )),
+    }
+}
+
+fn string<'a>(value: &'a Value, path: &str) -> Result<&'a str, JsonError> {
+    match value {
+        Value::String(s) => Ok(s),
+        _ => Err(error(
+            path,
+            format!("Expected a string but found {}", value.type_name()),
+        )),
+    }
+}
+
+fn child(value: &Value, path: &str, name: &str, depth: usize) -> Result<Box<Expr>, JsonError> {
+    let path = format!("{path}.{name}");
+    Ok(Box::new(expr(
+        field(value, &path, name)?,
+        &path,
+        depth + 1,
+    )?))
+}
+
+// `depth` is how many nodes deep this one is. values from `json::parse`
+// can't nest too deep to read, but ones built by hand for `from_value` can.
+// every node kind is read by its own function, so the frames that recurse
+// only hold what one kind needs and a tree MAX_DEPTH deep fits on the stack
+fn expr(value: &Value, path: &str, depth: usize) -> Result<Expr, JsonError> {
+    if depth == json::MAX_DEPTH {
+        return Err(error(
+            path,
+            format!("Tree is more than {} nodes deep", json::MAX_DEPTH),
+        ));
+    }
+    let kind = string(field(value, path, "kind")?, &format!("{path}.kind"))?;
+    let span = optional_span(value, path)?;
+    match kind {
+        "Binary" => binary(value, path, depth, span),
+        "Conditional" => conditional(value, path, depth, span),
+        "Grouping" => grouping(value, path, depth, span),
+        "Literal" => Ok(Expr::Literal(LiteralExpr::new(
+            literal(field(value, path, "value")?, &format!("{path}.value"))?,
+            span,
+        ))),
+        "Unary" => unary(value, path, depth, span),
+        _ => Err(error(
+            &format!("{path}.kind"),
+            format!("Unknown node kind `{kind}`"),
+        )),
+    }
+}
+
+fn binary(value: &Value, path: &str, depth: usize, span: Span) -> Result<Expr, JsonError> {
+    let operator = operator(value, path)?;
+    // `?` has an infix rule too, but it makes a conditional
+    let (precedence, _) = infix_binding(operator.kind);
+    if precedence == Precedence::None || operator.kind == TokenKind::Question {
+        return Err(error(
+            &format!("{path}.operator"),
+            format!("`{}` is not a binary operator", operator.kind),
+        ));
+    }
+    Ok(Expr::Binary(BinaryExpr::new(
+        child(value, path, "left", depth)?,
+        operator,
+        child(value, path, "right", depth)?,
+        span,
+    )))
+}
+
+fn conditional(value: &Value, path: &str, depth: usize, span: Span) -> Result<Expr, JsonError> {
+    Ok(Expr::Conditional(ConditionalExpr::new(
+        child(value, path, "condition", depth)?,
+        child(value, path, "then_branch", depth)?,
+        child(value, path, "else_branch", depth)?,
+        span,
+    )))
+}
+
+fn grouping(value: &Value, path: &str, depth: usize, span: Span) -> Result<Expr, JsonError> {
+    Ok(Expr::Grouping(GroupingExpr::new(
+        child(value, path, "expression", depth)?,
+        span,
+    )))
+}
+
+fn unary(value: &Value, path: &str, depth: usize, span: Span) -> Result<Expr, JsonError> {
+    let operator = operator(value, path)?;
+    if !matches!(operator.kind, TokenKind::Minus | TokenKind::Bang) {
+        return Err(error(
+            &format!("{path}.operator"),
+            format!("`{}` is not a unary operator", operator.kind),
+        ));
+    }
+    Ok(Expr::Unary(UnaryExpr::new(
+        operator,
+        child(value, path, "right", depth)?,
+        span,
+    )))
+}
+
+fn operator(node: &Value, path: &str) -> Result<Token, JsonError> {
+    let path = format!("{path}.operator");
+    let value = field(node, &path, "operator")?;
+    let name = string(field(value, &path, "kind")?, &format!("{path}.kind"))?;
+    let kind = TokenKind::from_name(name).ok_or_else(|| {
+        error(
+            &format!("{path}.kind"),
+            format!("Unknown token kind `{name}`"),
+        )
+    })?;
+    let lexeme = match value.get("lexeme") {
+        Some(lexeme) => string(lexeme, &format!("{path}.lexeme"))?.to_string(),
+        None => kind.spelling().unwrap_or_default().to_string(),
+    };
+    let span = optional_span(value, &path)?;
+    Ok(Token::new(kind, lexeme, span.line).span(span))
+}
+
+// the `span` field of a node or token, which can be left out
+fn optional_span(value: &Value, path: &str) -> Result<Span, JsonError> {
+    match value.get("span") {
+        Some(span) => self::span(span, &format!("{path}.span")),
+        None => Ok(Span::default()),
+    }
+}
+
+fn span(value: &Value, path: &str) -> Result<Span, JsonError> {
+    let mut numbers = [0; 4];
+    for (number, name) in numbers.iter_mut().zip(["start", "end", "line", "column"]) {
+        *number = match field(value, path, name)? {
+            Value::Number(n) if *n >= 0.0 && n.fract() == 0.0 => *n as usize,
+            other => {
+                return Err(error(
+                    &format!("{path}.{name}"),
+                    format!("Expected a whole number but found {}", other.type_name()),
+                ))
+            }
+        };
+    }
+    let [start, end, line, column] = numbers;
+    Ok(Span::new(start, end, line as u32, column as u32))
+}
+
+fn literal(value: &Value, path: &str) -> Result<Literal, JsonError> {
+    let ty = string(field(value, path, "type")?, &format!("{path}.type"))?;
+    let val = || field(value, path, "value");
+    let mismatch = |found: &Value| {
+        error(
+            &format!("{path}.value"),
+            format!("Expected a {ty} value but found {}", found.type_name()),
+        )
+    };
+    match ty {
+        "number" => match val()? {
+            Value::Number(n) if n.is_finite() => Ok(Literal::Num { val: *n }),
+            // only a hand built value can hold one of these
+            Value::Number(_) => Err(error(
+                &format!("{path}.value"),
+                "Numbers that aren't finite are written as \"NaN\", \"inf\" or \"-inf\""
+                    .to_string(),
+            )),
+            Value::String(s) if s == "NaN" => Ok(Literal::Num { val: f64::NAN }),
+            Value::String(s) if s == "inf" => Ok(Literal::Num { val: f64::INFINITY }),
+            Value::String(s) if s == "-inf" => Ok(Literal::Num {
+                val: f64::NEG_INFINITY,
+            }),
+            other => Err(mismatch(other)),
+        },
+        "string" => match val()? {
+            Value::String(s) => Ok(Literal::Str {
+                val: s.clone(),
+                terminated: true,
+            }),
+            other => Err(mismatch(other)),
+        },
+        "bool" => match val()? {
+            Value::Bool(b) => Ok(Literal::Bool { val: *b }),
+            other => Err(mismatch(other)),
+        },
+        "nil" => Ok(Literal::Nil),
+        _ => Err(error(
+            &format!("{path}.type"),
+            format!("Unknown literal type `{ty}`"),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_json, from_value, to_json};
+    use crate::json::{self, Value};
+    use crate::lexer::scan_tokens;
+    use crate::lexer::token::{Literal, Span};
+    use crate::parser::parse;
+    use crate::syntax::expr::*;
+    use crate::syntax::Structural;
+
+    fn num(val: f64) -> Expr {
+        Expr::Literal(LiteralExpr::new(Literal::Num { val }, Span::default()))
+    }
+
+    fn assert_round_trips(tree: &Expr) {
+        let text = to_json(tree);
+        let read = from_json(&text).unwrap();
+        assert!(read.structural_eq(tree), "{text}");
+        // spans and lexemes come back too
+        assert_eq!(to_json(&read), text);
+    }
+
+    // the message of the error reading the json gives
+    fn error(text: &str) -> String {
+        from_json(text).unwrap_err().message
+    }
+
+    #[test]
+    fn every_node_kind_round_trips() {
+        for source in [
+            "1 + 2 * 3",
+            "1, 2",
+            "1 == 2 != 3 < 4 <= 5 > 6 >= 7 - 8 / 9",
+            "true ? \"a\" : nil",
+            "((1))",
+            "-1",
+            "!false",
+            // lox strings have no escapes, but json has to escape these
+            "\"a \\ backslash, a\nnewline and ünïcödé\"",
+            "1500.5 + 0.25",
+        ] {
+            assert_round_trips(&parse(scan_tokens(source)).unwrap());
+        }
+    }
+
+    #[test]
+    fn numbers_without_a_json_literal_round_trip() {
+        for val in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY, -0.0, 1e300] {
+            assert_round_trips(&num(val));
+        }
+    }
+
+    #[test]
+    fn spans_and_lexemes_can_be_left_out() {
+        let read = from_json(
+            r#"{"kind":"Unary","operator":{"kind":"Minus"},"right":{"kind":"Literal","value":{"type":"number","value":1}}}"#,
+        )
+        .unwrap();
+        assert!(read.structural_eq(&parse(scan_tokens("-1")).unwrap()));
+        assert_eq!(read.span(), Span::default());
+    }
+
+    #[test]
+    fn unknown_operators_are_rejected() {
+        let binary = |op: &str| {
+            format!(
+                r#"{{"kind":"Binary","left":{{"kind":"Literal","value":{{"type":"nil"}}}},"operator":{{"kind":"{op}"}},"right":{{"kind":"Literal","value":{{"type":"nil"}}}}}}"#
+            )
+        };
+        assert_eq!(
+            error(&binary("Caret")),
+            "Unknown token kind `Caret` at `$.operator.kind`"
+        );
+        assert_eq!(
+            error(&binary("Bang")),
+            "`Bang` is not a binary operator at `$.operator`"
+        );
+        assert_eq!(
+            error(&binary("Question")),
+            "`Question` is not a binary operator at `$.operator`"
+        );
+        assert_eq!(
+            error(
+                r#"{"kind":"Unary","operator":{"kind":"Plus"},"right":{"kind":"Literal","value":{"type":"nil"}}}"#
+            ),
+            "`Plus` is not a unary operator at `$.operator`"
+        );
+        assert_eq!(
+            error(r#"{"kind":"Ternary"}"#),
+            "Unknown node kind `Ternary` at `$.kind`"
+        );
+    }
+
+    #[test]
+    fn missing_fields_are_rejected() {
+        assert_eq!(error("{}"), "Missing field `kind` at `$`");
+        assert_eq!(
+            error(r#"{"kind":"Grouping"}"#),
+            "Missing field `expression` at `$.expression`"
+        );
+        assert_eq!(
+            error(r#"{"kind":"Literal","value":{"type":"number"}}"#),
+            "Missing field `value` at `$.value`"
+        );
+        assert_eq!(
+            error(r#"{"kind":"Unary","right":{"kind":"Literal","value":{"type":"nil"}}}"#),
+            "Missing field `operator` at `$.operator`"
+        );
+        assert_eq!(
+            error(
+                r#"{"kind":"Grouping","expression":{"kind":"Literal","value":{"type":"nil"},"span":{"start":0,"end":1,"line":1}}}"#
+            ),
+            "Missing field `column` at `$.expression.span`"
+        );
+    }
+
+    #[test]
+    fn non_finite_numbers_are_rejected() {
+        // the only way to write them is as the strings
+        assert_eq!(
+            error(r#"{"kind":"Literal","value":{"type":"number","value":1e999}}"#),
+            "Number `1e999` is too large"
+        );
+        assert_eq!(
+            error(r#"{"kind":"Literal","value":{"type":"number","value":"Infinity"}}"#),
+            "Expected a number value but found string at `$.value.value`"
+        );
+        let text = |s: &str| Value::String(s.to_string());
+        let value = Value::Object(vec![
+            ("kind".to_string(), text("Literal")),
+            (
+                "value".to_string(),
+                Value::Object(vec![
+                    ("type".to_string(), text("number")),
+                    ("value".to_string(), Value::Number(f64::INFINITY)),
+                ]),
+            ),
+        ]);
+        assert!(from_value(&value)
+            .unwrap_err()
+            .message
+            .contains("aren't finite"));
+    }
+
+    #[test]
+    fn hand_built_values_cant_nest_too_deep() {
+        let mut value = json::parse(r#"{"kind":"Literal","value":{"type":"nil"}}"#).unwrap();
+        for _ in 0..json::MAX_DEPTH {
+            value = Value::Object(vec![
+                ("kind".to_string(), Value::String("Grouping".to_string())),
+                ("expression".to_string(), value),
+            ]);
+        }
+        assert!(from_value(&value)
+            .unwrap_err()
+            .message
+            .starts_with("Tree is more than"));
+    }
+}
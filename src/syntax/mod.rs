@@ -1 +1,10 @@
+pub mod arena_expr;
+pub mod cst;
 pub mod expr;
+pub mod json;
+mod node_id;
+pub mod printer;
+mod structural;
+
+pub use node_id::NodeId;
+pub use structural::{Structural, StructuralKey};
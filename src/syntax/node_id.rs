@@ -0,0 +1,23 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+
+static NEXT_NODE_ID: AtomicU32 = AtomicU32::new(0);
+
+// unique identifier for a syntax tree node, so passes like a resolver or
+// debugger can keep side tables keyed by node instead of by address.
+// every node the parser builds gets a fresh one, cloning a tree keeps
+// them, the clone is the same nodes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeId(pub u32);
+
+impl NodeId {
+    // hand out an id no other node has been given
+    pub fn fresh() -> NodeId {
+        NodeId(NEXT_NODE_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+impl std::fmt::Display for NodeId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
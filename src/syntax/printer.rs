@@ -0,0 +1,595 @@
+use std::cell::{Cell, RefCell};
+
+use crate::lexer::token::TokenKind;
+use crate::parser::{infix_binding, Associativity, Precedence};
+use crate::syntax::arena_expr::{self, ExprArena, ExprRef};
+use crate::syntax::expr::*;
+
+pub struct AstPrinter;
+impl AstPrinter {
+    pub fn print(&self, e: &Expr) -> String {
+        e.walk_expr(self)
+    }
+}
+impl ExprVisitor<String> for AstPrinter {
+    fn visit_binary_expr(&self, b: &BinaryExpr) -> String {
+        self.parenthesize(&b.operator.lexeme, &[&b.left, &b.right])
+    }
+    fn visit_conditional_expr(&self, c: &ConditionalExpr) -> String {
+        self.parenthesize("?:", &[&c.condition, &c.then_branch, &c.else_branch])
+    }
+    fn visit_grouping_expr(&self, g: &GroupingExpr) -> String {
+        self.parenthesize("group", &[&g.expression])
+    }
+    fn visit_literal_expr(&self, l: &LiteralExpr) -> String {
+        match &l.value {
+            Literal::Num { val } => val.to_string(),
+            Literal::Str { val, .. } => val.clone(),
+            Literal::Bool { val } => val.to_string(),
+            Literal::Nil => "null".to_string(),
+        }
+    }
+    fn visit_unary_expr(&self, u: &UnaryExpr) -> String {
+        self.parenthesize(&u.operator.lexeme, &[&u.right])
+    }
+}
+
+impl AstPrinter {
+    pub fn parenthesize(&self, name: &str, exprs: &[&Expr]) -> String {
+        let mut ret: String = format!("({name}");
+        for x in exprs {
+            let some = format!(" {}", x.walk_expr(self));
+            ret.push_str(some.as_str());
+        }
+        ret.push(')');
+        ret
+    }
+}
+
+// the same printer over an arena backed tree, output is identical
+// to printing the equivalent boxed tree
+impl AstPrinter {
+    pub fn print_arena(&self, arena: &ExprArena, e: ExprRef) -> String {
+        arena.walk_expr(e, self)
+    }
+
+    pub fn parenthesize_arena(&self, arena: &ExprArena, name: &str, exprs: &[ExprRef]) -> String {
+        let mut ret: String = format!("({name}");
+        for x in exprs {
+            let some = format!(" {}", arena.walk_expr(*x, self));
+            ret.push_str(some.as_str());
+        }
+        ret.push(')');
+        ret
+    }
+}
+
+impl arena_expr::ExprVisitor<String> for AstPrinter {
+    fn visit_binary_expr(&self, arena: &ExprArena, b: &arena_expr::BinaryExpr) -> String {
+        self.parenthesize_arena(arena, &b.operator.lexeme, &[b.left, b.right])
+    }
+    fn visit_conditional_expr(&self, arena: &ExprArena, c: &arena_expr::ConditionalExpr) -> String {
+        self.parenthesize_arena(arena, "?:", &[c.condition, c.then_branch, c.else_branch])
+    }
+    fn visit_grouping_expr(&self, arena: &ExprArena, g: &arena_expr::GroupingExpr) -> String {
+        self.parenthesize_arena(arena, "group", &[g.expression])
+    }
+    fn visit_literal_expr(&self, _: &ExprArena, l: &arena_expr::LiteralExpr) -> String {
+        match &l.value {
+            Literal::Num { val } => val.to_string(),
+            Literal::Str { val, .. } => val.clone(),
+            Literal::Bool { val } => val.to_string(),
+            Literal::Nil => "null".to_string(),
+        }
+    }
+    fn visit_unary_expr(&self, arena: &ExprArena, u: &arena_expr::UnaryExpr) -> String {
+        self.parenthesize_arena(arena, &u.operator.lexeme, &[u.right])
+    }
+}
+
+// prints a tree in reverse polish notation, operands first and then the
+// operator, e.g. `(1 + 2) * -3` is `1 2 + 3 neg *`. every operator word
+// has one meaning so the output can be read back with `rpn::read`, unary
+// minus is `neg` to tell it apart from subtraction. groupings don't
+// print anything, the order of the words already says what they did
+pub struct ReversePolishPrinter {}
+impl ReversePolishPrinter {
+    pub fn print(&self, e: &Expr) -> String {
+        e.walk_expr(self)
+    }
+
+    pub fn infix_to_polish(&self, binary_expr: &BinaryExpr) -> String {
+        format!(
+            "{} {} {}",
+            binary_expr.left.walk_expr(self),
+            binary_expr.right.walk_expr(self),
+            binary_expr.operator.lexeme
+        )
+    }
+}
+
+impl ExprVisitor<String> for ReversePolishPrinter {
+    fn visit_binary_expr(&self, b: &BinaryExpr) -> String {
+        self.infix_to_polish(b)
+    }
+    fn visit_conditional_expr(&self, c: &ConditionalExpr) -> String {
+        format!(
+            "{} {} {} ?:",
+            c.condition.walk_expr(self),
+            c.then_branch.walk_expr(self),
+            c.else_branch.walk_expr(self)
+        )
+    }
+    fn visit_grouping_expr(&self, g: &GroupingExpr) -> String {
+        g.expression.walk_expr(self)
+    }
+    fn visit_literal_expr(&self, l: &LiteralExpr) -> String {
+        // strings keep their quotes so ones with spaces stay one word.
+        // numbers print as rust does, `inf` and `NaN` included, and the
+        // reader parses them the same way
+        match &l.value {
+            Literal::Num { val } => val.to_string(),
+            Literal::Str { val, .. } => format!("\"{val}\""),
+            Literal::Bool { val } => val.to_string(),
+            Literal::Nil => "nil".to_string(),
+        }
+    }
+    fn visit_unary_expr(&self, u: &UnaryExpr) -> String {
+        let operator = match u.operator.kind {
+            TokenKind::Minus => "neg",
+            _ => &u.operator.lexeme,
+        };
+        format!("{} {}", u.right.walk_expr(self), operator)
+    }
+}
+
+// prints a tree back out as lox source. grouping nodes keep their parens,
+// and other parens are only added where precedence or associativity needs
+// them, e.g. for trees built by the optimizer, so parsing the output of a
+// tree that came from the parser gives back a structurally identical tree
+pub struct SourcePrinter;
+impl SourcePrinter {
+    pub fn print(&self, e: &Expr) -> String {
+        self.operand(e, Precedence::Comma)
+    }
+
+    // how tightly the expression holds together when printed without parens
+    fn precedence(e: &Expr) -> Precedence {
+        match e {
+            Expr::Binary(b) => infix_binding(b.operator.kind).0,
+            Expr::Conditional(_) => Precedence::Conditional,
+            Expr::Unary(_) => Precedence::Unary,
+            // a negative number prints with a leading `-`, and so
+            // parses as a negation
+            Expr::Literal(LiteralExpr {
+                value: Literal::Num { val },
+                ..
+            }) if val.is_sign_negative() => Precedence::Unary,
+            Expr::Grouping(_) | Expr::Literal(_) => Precedence::Primary,
+        }
+    }
+
+    // print an operand that has to bind at least as tightly as `min`
+    // to stay in place when parsed, parenthesizing it if it doesn't
+    fn operand(&self, e: &Expr, min: Precedence) -> String {
+        let source = e.walk_expr(self);
+        if Self::precedence(e) < min {
+            format!("({source})")
+        } else {
+            source
+        }
+    }
+}
+
+impl ExprVisitor<String> for SourcePrinter {
+    fn visit_binary_expr(&self, b: &BinaryExpr) -> String {
+        let (precedence, associativity) = infix_binding(b.operator.kind);
+        let (left, right) = match associativity {
+            Associativity::Left => (precedence, precedence.next()),
+            Associativity::Right => (precedence.next(), precedence),
+        };
+        let separator = match b.operator.kind {
+            TokenKind::Comma => "",
+            _ => " ",
+        };
+        format!(
+            "{}{}{} {}",
+            self.operand(&b.left, left),
+            separator,
+            b.operator.lexeme,
+            self.operand(&b.right, right)
+        )
+    }
+    fn visit_conditional_expr(&self, c: &ConditionalExpr) -> String {
+        // `?:` is right associative, and the middle operand is delimited
+        // by the `?` and `:` so it can be anything
+        format!(
+            "{} ? {} : {}",
+            self.operand(&c.condition, Precedence::Conditional.next()),
+            self.operand(&c.then_branch, Precedence::Comma),
+            self.operand(&c.else_branch, Precedence::Conditional)
+        )
+    }
+    fn visit_grouping_expr(&self, g: &GroupingExpr) -> String {
+        format!("({})", self.operand(&g.expression, Precedence::Comma))
+    }
+    fn visit_literal_expr(&self, l: &LiteralExpr) -> String {
+        match &l.value {
+            // lox has no literals for these, so spell out a division
+            // that gives them
+            Literal::Num { val } if val.is_nan() => "(0 / 0)".to_string(),
+            Literal::Num { val } if val.is_infinite() => {
+                format!("({}1 / 0)", if *val < 0.0 { "-" } else { "" })
+            }
+            Literal::Num { val } => val.to_string(),
+            Literal::Str { val, .. } => format!("\"{val}\""),
+            Literal::Bool { val } => val.to_string(),
+            Literal::Nil => "nil".to_string(),
+        }
+    }
+    fn visit_unary_expr(&self, u: &UnaryExpr) -> String {
+        format!(
+            "{}{}",
+            u.operator.lexeme,
+            self.operand(&u.right, Precedence::Unary)
+        )
+    }
+}
+
+// prints a tree as a graphviz digraph, e.g. for `dot -Tsvg`. every expr
+// gets a node labelled with its operator or literal, with edges to its
+// children in source order. the visitor only gets `&self`, so the nodes
+// and edges are collected through cells as the tree is walked
+pub struct DotPrinter {
+    next_id: Cell<usize>,
+    lines: RefCell<Vec<String>>,
+}
+impl DotPrinter {
+    pub fn new() -> DotPrinter {
+        DotPrinter {
+            next_id: Cell::new(0),
+            lines: RefCell::new(Vec::new()),
+        }
+    }
+
+    pub fn print(&self, e: &Expr) -> String {
+        self.next_id.set(0);
+        self.lines.borrow_mut().clear();
+        e.walk_expr(self);
+        let mut ret = String::from("digraph ast {\n    node [shape=box];\n");
+        for line in self.lines.borrow().iter() {
+            ret.push_str("    ");
+            ret.push_str(line);
+            ret.push('\n');
+        }
+        ret.push('}');
+        ret
+    }
+
+    // add a node, then its children and the edges to them, and hand back
+    // its id. ids are given out before the children are walked, so they
+    // number the tree in pre-order
+    fn node(&self, label: &str, children: &[&Expr]) -> String {
+        let id = format!("n{}", self.next_id.replace(self.next_id.get() + 1));
+        self.lines
+            .borrow_mut()
+            .push(format!("{id} [label=\"{}\"];", escape(label)));
+        for child in children {
+            let child = child.walk_expr(self);
+            self.lines.borrow_mut().push(format!("{id} -> {child};"));
+        }
+        id
+    }
+}
+
+impl Default for DotPrinter {
+    fn default() -> DotPrinter {
+        DotPrinter::new()
+    }
+}
+
+impl ExprVisitor<String> for DotPrinter {
+    fn visit_binary_expr(&self, b: &BinaryExpr) -> String {
+        self.node(&b.operator.lexeme, &[&b.left, &b.right])
+    }
+    fn visit_conditional_expr(&self, c: &ConditionalExpr) -> String {
+        self.node("?:", &[&c.condition, &c.then_branch, &c.else_branch])
+    }
+    fn visit_grouping_expr(&self, g: &GroupingExpr) -> String {
+        self.node("group", &[&g.expression])
+    }
+    fn visit_literal_expr(&self, l: &LiteralExpr) -> String {
+        let label = match &l.value {
+            Literal::Num { val } => val.to_string(),
+            Literal::Str { val, .. } => format!("\"{val}\""),
+            Literal::Bool { val } => val.to_string(),
+            Literal::Nil => "nil".to_string(),
+        };
+        self.node(&label, &[])
+    }
+    fn visit_unary_expr(&self, u: &UnaryExpr) -> String {
+        self.node(&u.operator.lexeme, &[&u.right])
+    }
+}
+
+// escape a label for use inside a double quoted dot string
+fn escape(label: &str) -> String {
+    let mut ret = String::with_capacity(label.len());
+    for c in label.chars() {
+        match c {
+            '"' | '\\' => {
+                ret.push('\\');
+                ret.push(c);
+            }
+            '\n' => ret.push_str("\\n"),
+            _ => ret.push(c),
+        }
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AstPrinter, DotPrinter, SourcePrinter};
+    use crate::lexer::scan_tokens;
+    use crate::lexer::token::{Literal, Span, Token, TokenKind};
+    use crate::parser::parse;
+    use crate::syntax::expr::*;
+    use crate::syntax::Structural;
+
+    fn parse_str(source: &str) -> Expr {
+        parse(scan_tokens(source)).unwrap()
+    }
+
+    fn num(val: f64) -> Expr {
+        Expr::Literal(LiteralExpr::new(Literal::Num { val }, Span::default()))
+    }
+
+    fn operator(kind: TokenKind) -> Token {
+        Token::new(kind, kind.spelling().unwrap().to_string(), 1)
+    }
+
+    fn binary(left: Expr, kind: TokenKind, right: Expr) -> Expr {
+        Expr::Binary(BinaryExpr::new(
+            Box::new(left),
+            operator(kind),
+            Box::new(right),
+            Span::default(),
+        ))
+    }
+
+    fn unary(kind: TokenKind, right: Expr) -> Expr {
+        Expr::Unary(UnaryExpr::new(
+            operator(kind),
+            Box::new(right),
+            Span::default(),
+        ))
+    }
+
+    fn conditional(condition: Expr, then_branch: Expr, else_branch: Expr) -> Expr {
+        Expr::Conditional(ConditionalExpr::new(
+            Box::new(condition),
+            Box::new(then_branch),
+            Box::new(else_branch),
+            Span::default(),
+        ))
+    }
+
+    // drops the groupings parsing the printed source adds back, so it can
+    // be compared with a tree that never had them
+    struct Ungroup;
+    impl ExprFold for Ungroup {
+        fn fold_grouping_expr(&mut self, g: GroupingExpr) -> Expr {
+            self.fold_expr(*g.expression)
+        }
+    }
+
+    // print a tree without groupings, check the source and that it parses
+    // back to the same tree
+    fn assert_prints(tree: Expr, expected: &str) {
+        let source = SourcePrinter.print(&tree);
+        assert_eq!(source, expected);
+        let reparsed = Ungroup.fold_expr(parse_str(&source));
+        assert!(
+            reparsed.structural_eq(&tree),
+            "{expected} parsed as {}",
+            AstPrinter.print(&reparsed)
+        );
+    }
+
+    #[test]
+    fn parsed_trees_print_as_they_were_written() {
+        for source in [
+            "1 + 2 * 3",
+            "(1 + 2) * 3",
+            "1 - 2 - 3",
+            "1 - (2 - 3)",
+            "-(1 + 2)",
+            "!!true",
+            "-1 * -2",
+            "1 ? 2 : 3 ? 4 : 5",
+            "(1 ? 2 : 3) ? 4 : 5",
+            "1 ? 2, 3 : 4",
+            "1, 2, 3",
+            "1, (2, 3)",
+            "\"a\" + nil == false",
+        ] {
+            let tree = parse_str(source);
+            let printed = SourcePrinter.print(&tree);
+            assert_eq!(printed, source);
+            assert!(parse_str(&printed).structural_eq(&tree), "{source}");
+        }
+    }
+
+    #[test]
+    fn precedence_adds_parens() {
+        use TokenKind::*;
+        assert_prints(
+            binary(binary(num(1.0), Plus, num(2.0)), Star, num(3.0)),
+            "(1 + 2) * 3",
+        );
+        assert_prints(
+            binary(num(1.0), Star, binary(num(2.0), Plus, num(3.0))),
+            "1 * (2 + 3)",
+        );
+        assert_prints(
+            binary(num(1.0), Plus, binary(num(2.0), Star, num(3.0))),
+            "1 + 2 * 3",
+        );
+        assert_prints(
+            binary(binary(num(1.0), Less, num(2.0)), EqualEqual, num(3.0)),
+            "1 < 2 == 3",
+        );
+        assert_prints(unary(Bang, binary(num(1.0), Greater, num(2.0))), "!(1 > 2)");
+        assert_prints(unary(Minus, unary(Minus, num(1.0))), "--1");
+    }
+
+    #[test]
+    fn associativity_adds_parens() {
+        use TokenKind::*;
+        assert_prints(
+            binary(binary(num(1.0), Minus, num(2.0)), Minus, num(3.0)),
+            "1 - 2 - 3",
+        );
+        assert_prints(
+            binary(num(1.0), Minus, binary(num(2.0), Minus, num(3.0))),
+            "1 - (2 - 3)",
+        );
+        assert_prints(
+            binary(num(1.0), Slash, binary(num(2.0), Star, num(3.0))),
+            "1 / (2 * 3)",
+        );
+        assert_prints(
+            binary(binary(num(1.0), Comma, num(2.0)), Comma, num(3.0)),
+            "1, 2, 3",
+        );
+        assert_prints(
+            binary(num(1.0), Comma, binary(num(2.0), Comma, num(3.0))),
+            "1, (2, 3)",
+        );
+    }
+
+    #[test]
+    fn conditionals_in_every_operand_position() {
+        use TokenKind::*;
+        let inner = || conditional(num(1.0), num(2.0), num(3.0));
+        assert_prints(
+            conditional(inner(), num(4.0), num(5.0)),
+            "(1 ? 2 : 3) ? 4 : 5",
+        );
+        assert_prints(
+            conditional(num(4.0), inner(), num(5.0)),
+            "4 ? 1 ? 2 : 3 : 5",
+        );
+        assert_prints(
+            conditional(num(4.0), num(5.0), inner()),
+            "4 ? 5 : 1 ? 2 : 3",
+        );
+        assert_prints(
+            conditional(inner(), inner(), inner()),
+            "(1 ? 2 : 3) ? 1 ? 2 : 3 : 1 ? 2 : 3",
+        );
+        assert_prints(binary(inner(), Plus, num(4.0)), "(1 ? 2 : 3) + 4");
+        assert_prints(binary(num(4.0), Plus, inner()), "4 + (1 ? 2 : 3)");
+        assert_prints(unary(Minus, inner()), "-(1 ? 2 : 3)");
+        assert_prints(binary(inner(), Comma, inner()), "1 ? 2 : 3, 1 ? 2 : 3");
+        assert_prints(
+            conditional(
+                binary(num(1.0), Comma, num(2.0)),
+                binary(num(3.0), Comma, num(4.0)),
+                binary(num(5.0), Comma, num(6.0)),
+            ),
+            "(1, 2) ? 3, 4 : (5, 6)",
+        );
+    }
+
+    // a negative number prints with its sign, so it reads back as a
+    // negation. it still has to be parenthesized like one
+    #[test]
+    fn negative_numbers_print_like_negations() {
+        use TokenKind::*;
+        let cases = [
+            (unary(Minus, num(-3.0)), "--3", "(- (- 3))"),
+            (unary(Bang, num(-3.0)), "!-3", "(! (- 3))"),
+            (binary(num(1.0), Minus, num(-3.0)), "1 - -3", "(- 1 (- 3))"),
+            (binary(num(-3.0), Star, num(2.0)), "-3 * 2", "(* (- 3) 2)"),
+            (
+                conditional(num(-1.0), num(-2.0), num(-3.0)),
+                "-1 ? -2 : -3",
+                "(?: (- 1) (- 2) (- 3))",
+            ),
+            (
+                unary(Minus, num(f64::NEG_INFINITY)),
+                "-(-1 / 0)",
+                "(- (group (/ (- 1) 0)))",
+            ),
+        ];
+        for (tree, source, sexpr) in cases {
+            assert_eq!(SourcePrinter.print(&tree), source);
+            assert_eq!(AstPrinter.print(&parse_str(source)), sexpr);
+        }
+    }
+
+    #[test]
+    fn dot_nodes_are_numbered_in_pre_order() {
+        assert_eq!(
+            DotPrinter::new().print(&parse_str("-1 + (2 ? 3 : 4)")),
+            "digraph ast {
+    node [shape=box];
+    n0 [label=\"+\"];
+    n1 [label=\"-\"];
+    n2 [label=\"1\"];
+    n1 -> n2;
+    n0 -> n1;
+    n3 [label=\"group\"];
+    n4 [label=\"?:\"];
+    n5 [label=\"2\"];
+    n4 -> n5;
+    n6 [label=\"3\"];
+    n4 -> n6;
+    n7 [label=\"4\"];
+    n4 -> n7;
+    n3 -> n4;
+    n0 -> n3;
+}"
+        );
+    }
+
+    #[test]
+    fn dot_numbering_starts_over_for_every_tree() {
+        let printer = DotPrinter::new();
+        let first = printer.print(&parse_str("1 + 2"));
+        assert_eq!(printer.print(&parse_str("1 + 2")), first);
+        assert_eq!(
+            printer.print(&parse_str("nil")),
+            "digraph ast {\n    node [shape=box];\n    n0 [label=\"nil\"];\n}"
+        );
+    }
+
+    #[test]
+    fn dot_edges_go_to_children_in_source_order() {
+        let dot = DotPrinter::new().print(&parse_str("1 - 2, true"));
+        let edges: Vec<&str> = dot.lines().filter(|line| line.contains("->")).collect();
+        assert_eq!(
+            edges,
+            vec![
+                "    n1 -> n2;",
+                "    n1 -> n3;",
+                "    n0 -> n1;",
+                "    n0 -> n4;"
+            ]
+        );
+    }
+
+    #[test]
+    fn dot_labels_are_escaped() {
+        // lox strings have no escapes, so a backslash or newline in one
+        // is the character itself
+        let dot = DotPrinter::new().print(&parse_str("\"a\\b\nc\" == \"\""));
+        assert!(
+            dot.contains("    n1 [label=\"\\\"a\\\\b\\nc\\\"\"];\n"),
+            "{dot}"
+        );
+        assert!(dot.contains("    n2 [label=\"\\\"\\\"\"];\n"), "{dot}");
+    }
+}
@@ -0,0 +1,108 @@
+use std::hash::{Hash, Hasher};
+
+use crate::lexer::token::{Literal, Token};
+
+// equality and hashing that only look at the shape of a tree and what's in
+// it, not where it came from. node ids, spans and the lines tokens were on
+// are ignored, so `1 + 2` parsed from two places in a file compares equal.
+// the impls for the nodes themselves are generated by define_ast
+pub trait Structural {
+    fn structural_eq(&self, other: &Self) -> bool;
+    fn structural_hash<H: Hasher>(&self, state: &mut H);
+}
+
+impl Structural for Token {
+    fn structural_eq(&self, other: &Self) -> bool {
+        self.kind == other.kind
+            && self.lexeme == other.lexeme
+            && self.literal.structural_eq(&other.literal)
+    }
+
+    fn structural_hash<H: Hasher>(&self, state: &mut H) {
+        self.kind.hash(state);
+        self.lexeme.hash(state);
+        self.literal.structural_hash(state);
+    }
+}
+
+impl Structural for Literal {
+    // numbers are compared by their bits so equality agrees with the hash,
+    // which makes NaN equal to itself and 0 different from -0
+    fn structural_eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Literal::Num { val: a }, Literal::Num { val: b }) => a.to_bits() == b.to_bits(),
+            _ => self == other,
+        }
+    }
+
+    fn structural_hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            Literal::Str { val, terminated } => {
+                val.hash(state);
+                terminated.hash(state);
+            }
+            Literal::Num { val } => val.to_bits().hash(state),
+            Literal::Bool { val } => val.hash(state),
+            Literal::Nil => {}
+        }
+    }
+}
+
+impl<T: Structural> Structural for Box<T> {
+    fn structural_eq(&self, other: &Self) -> bool {
+        (**self).structural_eq(other)
+    }
+
+    fn structural_hash<H: Hasher>(&self, state: &mut H) {
+        (**self).structural_hash(state)
+    }
+}
+
+impl<T: Structural> Structural for Option<T> {
+    fn structural_eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Some(a), Some(b)) => a.structural_eq(b),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+
+    fn structural_hash<H: Hasher>(&self, state: &mut H) {
+        self.is_some().hash(state);
+        if let Some(value) = self {
+            value.structural_hash(state);
+        }
+    }
+}
+
+impl<T: Structural> Structural for Vec<T> {
+    fn structural_eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().zip(other).all(|(a, b)| a.structural_eq(b))
+    }
+
+    fn structural_hash<H: Hasher>(&self, state: &mut H) {
+        self.len().hash(state);
+        for value in self {
+            value.structural_hash(state);
+        }
+    }
+}
+
+// wraps a reference so it can be used as a HashMap or HashSet key by its
+// structure, e.g. to find the repeated subexpressions in a tree
+pub struct StructuralKey<'a, T: Structural>(pub &'a T);
+
+impl<T: Structural> PartialEq for StructuralKey<'_, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.structural_eq(other.0)
+    }
+}
+
+impl<T: Structural> Eq for StructuralKey<'_, T> {}
+
+impl<T: Structural> Hash for StructuralKey<'_, T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.structural_hash(state)
+    }
+}